@@ -0,0 +1,103 @@
+use crate::primitive::{alias::TransformInt, cuboid::CuboidI, vector::Vec3I};
+
+// Opt-in record of the edits made to a voxel grid, enabled with VoxelGrid::track_changes and
+// drained with VoxelGrid::take_changes. Single cell edits are recorded as 1x1x1 regions, region
+// edits (fill, clear, paste) are recorded once for the whole region.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridChangeKind {
+    Created,
+    // The cells were overwritten, region edits writing values are always recorded as Updated even
+    // if some of the cells were empty before.
+    Updated,
+    Deleted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridChange {
+    pub kind: GridChangeKind,
+    pub region: CuboidI,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ChangeLog {
+    changes: Vec<GridChange>,
+}
+
+impl ChangeLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn record(&mut self, kind: GridChangeKind, region: &CuboidI) {
+        if region.is_empty() {
+            return;
+        }
+
+        self.changes.push(GridChange {
+            kind,
+            region: *region,
+        });
+    }
+
+    pub fn record_cell(
+        &mut self,
+        kind: GridChangeKind,
+        x: TransformInt,
+        y: TransformInt,
+        z: TransformInt,
+    ) {
+        self.changes.push(GridChange {
+            kind,
+            region: CuboidI {
+                position: Vec3I { x, y, z },
+                extents: Vec3I { x: 1, y: 1, z: 1 },
+            },
+        });
+    }
+
+    // Returns every change recorded since the last call, in the order they were made.
+    pub fn take(&mut self) -> Vec<GridChange> {
+        std::mem::take(&mut self.changes)
+    }
+}
+
+// Smallest cuboid containing every change, None if there are no changes.
+pub fn dirty_bounds(changes: &[GridChange]) -> Option<CuboidI> {
+    let mut changes = changes.iter();
+    let first = changes.next()?.region;
+
+    let mut min = first.position;
+    let mut max = Vec3I {
+        x: first.position.x + first.extents.x,
+        y: first.position.y + first.extents.y,
+        z: first.position.z + first.extents.z,
+    };
+
+    for change in changes {
+        let region = change.region;
+        min.x = min.x.min(region.position.x);
+        min.y = min.y.min(region.position.y);
+        min.z = min.z.min(region.position.z);
+        max.x = max.x.max(region.position.x + region.extents.x);
+        max.y = max.y.max(region.position.y + region.extents.y);
+        max.z = max.z.max(region.position.z + region.extents.z);
+    }
+
+    Some(CuboidI {
+        position: min,
+        extents: Vec3I {
+            x: max.x - min.x,
+            y: max.y - min.y,
+            z: max.z - min.z,
+        },
+    })
+}
@@ -1,20 +1,486 @@
 use crate::{
-    path::path::PathfindingType,
+    grid::{
+        change::{ChangeLog, GridChange, GridChangeKind},
+        region::VoxelRegion,
+    },
+    path::{
+        query::PathQuery,
+        result::{PathError, PathResult},
+    },
     primitive::{
+        alias::TransformInt,
         cuboid::CuboidI,
         vector::{Vec3F, Vec3I},
     },
 };
 
 pub trait PathfindingGrid: Send + Sync {
-    fn is_valid_path_node_i(&self, position: &Vec3I) -> bool;
+    // Cost of entering the node at position, None if the node can not be entered.
+    fn traversal_cost_i(&self, position: &Vec3I) -> Option<f32>;
+
+    fn is_valid_path_node_i(&self, position: &Vec3I) -> bool {
+        self.traversal_cost_i(position).is_some()
+    }
+
     fn position_i(&self) -> Vec3I;
     fn position_f(&self) -> Vec3F;
     fn as_cuboid_cells(&self) -> Vec<CuboidI>;
-    fn compute_path_i(
-        &self,
-        start: &Vec3I,
-        goal: &Vec3I,
-        pathfinding_engine: &PathfindingType,
-    ) -> Option<Vec<Vec3I>>;
+    // Runs the engine of the query against the grid.
+    fn compute_path_i(&self, query: &PathQuery) -> Result<PathResult, PathError>;
+}
+
+// Cell storage shared by every voxel grid backend, cell coordinates are world coordinates.
+// Operations on cells the grid can not hold (see contains_cell) are rejected.
+pub trait VoxelGrid<T: Copy + Clone + Default + Send + Sync> {
+    fn contains_cell(&self, x: TransformInt, y: TransformInt, z: TransformInt) -> bool;
+
+    // Stores value in an empty cell, returns false if the cell was already occupied.
+    fn create_cell(&mut self, x: TransformInt, y: TransformInt, z: TransformInt, value: &T)
+        -> bool;
+
+    fn read_cell(&self, x: TransformInt, y: TransformInt, z: TransformInt) -> Option<&T>;
+
+    fn read_cell_cloned(&self, x: TransformInt, y: TransformInt, z: TransformInt) -> Option<T> {
+        self.read_cell(x, y, z).cloned()
+    }
+
+    // Replaces the value of an occupied cell, returns false if the cell was empty.
+    fn update_cell(&mut self, x: TransformInt, y: TransformInt, z: TransformInt, value: &T)
+        -> bool;
+
+    // Stores value in the cell whether or not it was occupied.
+    fn upsert_cell(&mut self, x: TransformInt, y: TransformInt, z: TransformInt, value: &T);
+
+    fn delete_cell(&mut self, x: TransformInt, y: TransformInt, z: TransformInt) {
+        self.delete_cell_pop(x, y, z);
+    }
+
+    // Empties the cell, returns false if the cell was already empty.
+    fn delete_cell_check(&mut self, x: TransformInt, y: TransformInt, z: TransformInt) -> bool {
+        self.delete_cell_pop(x, y, z).is_some()
+    }
+
+    // Empties the cell and returns the value it held.
+    fn delete_cell_pop(&mut self, x: TransformInt, y: TransformInt, z: TransformInt) -> Option<T>;
+
+    // Storage for the change log of the grid, None while change tracking is disabled. Backends
+    // record their single cell edits into it, prefer track_changes and take_changes over
+    // accessing it directly.
+    fn change_log(&mut self) -> &mut Option<ChangeLog>;
+
+    // Enables or disables the change log, disabling it drops every change not yet taken.
+    fn track_changes(&mut self, enabled: bool) {
+        let change_log = self.change_log();
+        if enabled {
+            change_log.get_or_insert_with(ChangeLog::new);
+        } else {
+            *change_log = None;
+        }
+    }
+
+    // Returns every change made since the last call, empty while change tracking is disabled.
+    fn take_changes(&mut self) -> Vec<GridChange> {
+        match self.change_log() {
+            Some(change_log) => change_log.take(),
+            None => Vec::new(),
+        }
+    }
+
+    // Iterates every occupied cell along with its value.
+    fn iter<'a>(&'a self) -> impl Iterator<Item = (Vec3I, &'a T)>
+    where
+        T: 'a;
+
+    // Iterates every occupied cell inside of region along with its value.
+    fn iter_region<'a>(&'a self, region: &CuboidI) -> impl Iterator<Item = (Vec3I, &'a T)>
+    where
+        T: 'a,
+    {
+        region.iter_cells().filter_map(move |position| {
+            self.read_cell(position.x, position.y, position.z)
+                .map(|value| (position, value))
+        })
+    }
+
+    // Returns true if every cell of region can be held by the grid.
+    fn contains_region(&self, region: &CuboidI) -> bool {
+        if region.is_empty() {
+            return true;
+        }
+
+        let min = region.position;
+        let max = Vec3I {
+            x: min.x + region.extents.x - 1,
+            y: min.y + region.extents.y - 1,
+            z: min.z + region.extents.z - 1,
+        };
+
+        self.contains_cell(min.x, min.y, min.z) && self.contains_cell(max.x, max.y, max.z)
+    }
+
+    // Stores value in every cell of region. The edit is all or nothing, returns false without
+    // touching the grid if region is not fully contained by the grid.
+    fn fill_region(&mut self, region: &CuboidI, value: &T) -> bool {
+        if !self.contains_region(region) {
+            return false;
+        }
+
+        with_region_change(self, GridChangeKind::Updated, region, |grid| {
+            for position in region.iter_cells() {
+                grid.upsert_cell(position.x, position.y, position.z, value);
+            }
+        });
+
+        true
+    }
+
+    // Empties every cell of region, returns the number of cells that were occupied.
+    fn clear_region(&mut self, region: &CuboidI) -> usize {
+        with_region_change(self, GridChangeKind::Deleted, region, |grid| {
+            let mut cleared = 0;
+
+            for position in region.iter_cells() {
+                if grid.delete_cell_check(position.x, position.y, position.z) {
+                    cleared += 1;
+                }
+            }
+
+            cleared
+        })
+    }
+
+    // Copies region into a standalone buffer, cells outside of the grid are copied as empty.
+    fn copy_region(&self, region: &CuboidI) -> VoxelRegion<T> {
+        let mut buffer = VoxelRegion::new(&region.extents);
+
+        for (position, value) in self.iter_region(region) {
+            let local = Vec3I {
+                x: position.x - region.position.x,
+                y: position.y - region.position.y,
+                z: position.z - region.position.z,
+            };
+            buffer.set(&local, Some(*value));
+        }
+
+        buffer
+    }
+
+    // Replaces the cells of the region starting at offset with the contents of buffer, empty
+    // buffer cells empty the grid cell. The edit is all or nothing, returns false without touching
+    // the grid if the target region is not fully contained by the grid.
+    fn paste_region(&mut self, buffer: &VoxelRegion<T>, offset: &Vec3I) -> bool {
+        let region = CuboidI {
+            position: *offset,
+            extents: buffer.extents(),
+        };
+        if !self.contains_region(&region) {
+            return false;
+        }
+
+        with_region_change(self, GridChangeKind::Updated, &region, |grid| {
+            for (local, value) in buffer.iter() {
+                let x = local.x + offset.x;
+                let y = local.y + offset.y;
+                let z = local.z + offset.z;
+
+                match value {
+                    Some(value) => grid.upsert_cell(x, y, z, value),
+                    None => grid.delete_cell(x, y, z),
+                }
+            }
+        });
+
+        true
+    }
+}
+
+// Runs a region edit with the change log detached so the cells edited by it are not recorded one
+// by one, the whole region is recorded once afterwards instead.
+pub(crate) fn with_region_change<T, G, R>(
+    grid: &mut G,
+    kind: GridChangeKind,
+    region: &CuboidI,
+    edit: impl FnOnce(&mut G) -> R,
+) -> R
+where
+    T: Copy + Clone + Default + Send + Sync,
+    G: VoxelGrid<T> + ?Sized,
+{
+    let change_log = grid.change_log().take();
+    let result = edit(grid);
+
+    if let Some(mut change_log) = change_log {
+        change_log.record(kind, region);
+        *grid.change_log() = Some(change_log);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::voxel::{
+        chunked_voxel_grid::ChunkedVoxelGrid, dynamic_sized_voxel_grid::DynamicSizedVoxelGrid,
+        octree_voxel_grid::OctreeVoxelGrid, static_sized_voxel_grid::StaticSizedVoxelGridSmall,
+    };
+    use crate::{grid::change::dirty_bounds, path::cost::Traversable};
+
+    // Every backend has to agree on the semantics of the shared storage operations.
+    fn check_voxel_grid_semantics<G: VoxelGrid<u8>>(mut grid: G) {
+        assert!(grid.contains_cell(-2, -2, -2));
+        assert!(grid.read_cell(-2, -2, -2).is_none());
+
+        assert!(!grid.update_cell(-2, -2, -2, &1));
+        assert!(grid.create_cell(-2, -2, -2, &1));
+        assert!(!grid.create_cell(-2, -2, -2, &2));
+        assert_eq!(grid.read_cell_cloned(-2, -2, -2), Some(1));
+
+        assert!(grid.update_cell(-2, -2, -2, &3));
+        assert_eq!(grid.read_cell(-2, -2, -2), Some(&3));
+
+        grid.upsert_cell(1, 1, 1, &4);
+        grid.upsert_cell(1, 1, 1, &5);
+        assert_eq!(grid.read_cell_cloned(1, 1, 1), Some(5));
+
+        assert_eq!(grid.delete_cell_pop(1, 1, 1), Some(5));
+        assert_eq!(grid.delete_cell_pop(1, 1, 1), None);
+        assert!(grid.delete_cell_check(-2, -2, -2));
+        assert!(!grid.delete_cell_check(-2, -2, -2));
+
+        grid.upsert_cell(0, 0, 0, &6);
+        grid.delete_cell(0, 0, 0);
+        assert!(grid.read_cell(0, 0, 0).is_none());
+    }
+
+    fn check_voxel_grid_iteration<G: VoxelGrid<u8>>(mut grid: G) {
+        grid.upsert_cell(-3, 0, 2, &1);
+        grid.upsert_cell(0, 0, 0, &2);
+        grid.upsert_cell(3, -1, -4, &3);
+
+        let mut cells: Vec<(Vec3I, u8)> = grid
+            .iter()
+            .map(|(position, value)| (position, *value))
+            .collect();
+        cells.sort_by_key(|(_, value)| *value);
+        assert_eq!(
+            cells,
+            vec![
+                (Vec3I { x: -3, y: 0, z: 2 }, 1),
+                (Vec3I { x: 0, y: 0, z: 0 }, 2),
+                (Vec3I { x: 3, y: -1, z: -4 }, 3),
+            ]
+        );
+
+        let region = CuboidI {
+            position: Vec3I { x: 0, y: -8, z: -8 },
+            extents: Vec3I { x: 8, y: 9, z: 9 },
+        };
+        let mut cells: Vec<u8> = grid.iter_region(&region).map(|(_, value)| *value).collect();
+        cells.sort();
+        assert_eq!(cells, vec![2, 3]);
+    }
+
+    fn check_voxel_grid_regions<G: VoxelGrid<u8>>(mut grid: G) {
+        let region = CuboidI {
+            position: Vec3I {
+                x: -2,
+                y: -1,
+                z: -3,
+            },
+            extents: Vec3I { x: 3, y: 2, z: 4 },
+        };
+        assert!(grid.fill_region(&region, &9));
+        assert_eq!(grid.iter().count(), 24);
+        assert_eq!(grid.read_cell_cloned(0, 0, 0), Some(9));
+        assert!(grid.read_cell(1, 0, 0).is_none());
+
+        grid.delete_cell(-2, -1, -3);
+        let buffer = grid.copy_region(&region);
+        assert_eq!(buffer.extents(), region.extents);
+        assert!(buffer.get(&Vec3I { x: 0, y: 0, z: 0 }).is_none());
+        assert_eq!(buffer.get(&Vec3I { x: 2, y: 1, z: 3 }), Some(&9));
+
+        let offset = Vec3I { x: 0, y: -1, z: -3 };
+        assert!(grid.paste_region(&buffer, &offset));
+        assert!(grid.read_cell(0, -1, -3).is_none());
+        assert_eq!(grid.read_cell_cloned(2, 0, 0), Some(9));
+        assert_eq!(grid.iter().count(), 15 + 23);
+
+        assert_eq!(grid.clear_region(&region), 22);
+        assert_eq!(grid.iter().count(), 16);
+    }
+
+    fn check_voxel_grid_changes<G: VoxelGrid<u8>>(mut grid: G) {
+        let cell = |x, y, z| CuboidI {
+            position: Vec3I { x, y, z },
+            extents: Vec3I { x: 1, y: 1, z: 1 },
+        };
+
+        assert!(grid.create_cell(0, 0, 0, &1));
+        assert!(grid.take_changes().is_empty());
+
+        grid.track_changes(true);
+        assert!(grid.create_cell(1, 0, 0, &1));
+        assert!(grid.update_cell(1, 0, 0, &2));
+        grid.upsert_cell(0, 0, 0, &3);
+        grid.upsert_cell(2, 0, 0, &3);
+        assert!(grid.delete_cell_check(2, 0, 0));
+        assert!(!grid.delete_cell_check(2, 0, 0));
+        assert!(!grid.update_cell(3, 0, 0, &1));
+
+        let changes = grid.take_changes();
+        let expected = [
+            (GridChangeKind::Created, cell(1, 0, 0)),
+            (GridChangeKind::Updated, cell(1, 0, 0)),
+            (GridChangeKind::Updated, cell(0, 0, 0)),
+            (GridChangeKind::Created, cell(2, 0, 0)),
+            (GridChangeKind::Deleted, cell(2, 0, 0)),
+        ];
+        assert_eq!(changes.len(), expected.len());
+        for (change, (kind, region)) in changes.iter().zip(expected) {
+            assert_eq!(change.kind, kind);
+            assert_eq!(change.region, region);
+        }
+        assert!(grid.take_changes().is_empty());
+
+        // Region edits are recorded once for the whole region.
+        let region = CuboidI {
+            position: Vec3I {
+                x: -2,
+                y: -2,
+                z: -2,
+            },
+            extents: Vec3I { x: 2, y: 3, z: 2 },
+        };
+        assert!(grid.fill_region(&region, &4));
+        assert_eq!(grid.clear_region(&region), 12);
+        let changes = grid.take_changes();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].kind, GridChangeKind::Updated);
+        assert_eq!(changes[1].kind, GridChangeKind::Deleted);
+        assert_eq!(changes[1].region, region);
+
+        assert!(grid.create_cell(3, 3, 3, &1));
+        let changes = grid.take_changes();
+        assert_eq!(dirty_bounds(&changes), Some(cell(3, 3, 3)),);
+
+        grid.track_changes(false);
+        assert!(grid.delete_cell_check(3, 3, 3));
+        assert!(grid.take_changes().is_empty());
+    }
+
+    #[derive(Debug, Default, Clone, Copy, PartialEq)]
+    enum Terrain {
+        #[default]
+        Ground,
+        Mud,
+        Wall,
+    }
+
+    impl Traversable for Terrain {
+        fn is_passable(&self) -> bool {
+            *self != Terrain::Wall
+        }
+
+        fn traversal_cost(&self) -> f32 {
+            match self {
+                Terrain::Mud => 4.0,
+                _ => 1.0,
+            }
+        }
+    }
+
+    fn check_traversal_cost<G: VoxelGrid<Terrain> + PathfindingGrid>(mut grid: G) {
+        assert!(grid.create_cell(0, 0, 0, &Terrain::Ground));
+        assert!(grid.create_cell(1, 0, 0, &Terrain::Mud));
+        assert!(grid.create_cell(2, 0, 0, &Terrain::Wall));
+
+        assert_eq!(
+            grid.traversal_cost_i(&Vec3I { x: 0, y: 0, z: 0 }),
+            Some(1.0)
+        );
+        assert_eq!(
+            grid.traversal_cost_i(&Vec3I { x: 1, y: 0, z: 0 }),
+            Some(4.0)
+        );
+        assert_eq!(grid.traversal_cost_i(&Vec3I { x: 2, y: 0, z: 0 }), None);
+        assert_eq!(grid.traversal_cost_i(&Vec3I { x: 3, y: 0, z: 0 }), None);
+
+        assert!(grid.is_valid_path_node_i(&Vec3I { x: 1, y: 0, z: 0 }));
+        assert!(!grid.is_valid_path_node_i(&Vec3I { x: 2, y: 0, z: 0 }));
+    }
+
+    #[test]
+    fn backends_share_semantics() {
+        let position = Vec3I {
+            x: -4,
+            y: -4,
+            z: -4,
+        };
+        let extents = Vec3I { x: 8, y: 8, z: 8 };
+
+        check_voxel_grid_semantics(DynamicSizedVoxelGrid::<u8>::new(&position, &extents));
+        check_voxel_grid_semantics(
+            StaticSizedVoxelGridSmall::<u8>::new(&position, &extents).unwrap(),
+        );
+        check_voxel_grid_semantics(OctreeVoxelGrid::<u8>::new(&position, &extents));
+        check_voxel_grid_semantics(ChunkedVoxelGrid::<u8>::new());
+
+        check_voxel_grid_iteration(DynamicSizedVoxelGrid::<u8>::new(&position, &extents));
+        check_voxel_grid_iteration(
+            StaticSizedVoxelGridSmall::<u8>::new(&position, &extents).unwrap(),
+        );
+        check_voxel_grid_iteration(OctreeVoxelGrid::<u8>::new(&position, &extents));
+        check_voxel_grid_iteration(ChunkedVoxelGrid::<u8>::new());
+
+        check_voxel_grid_regions(DynamicSizedVoxelGrid::<u8>::new(&position, &extents));
+        check_voxel_grid_regions(
+            StaticSizedVoxelGridSmall::<u8>::new(&position, &extents).unwrap(),
+        );
+        check_voxel_grid_regions(OctreeVoxelGrid::<u8>::new(&position, &extents));
+        check_voxel_grid_regions(ChunkedVoxelGrid::<u8>::new());
+
+        check_voxel_grid_changes(DynamicSizedVoxelGrid::<u8>::new(&position, &extents));
+        check_voxel_grid_changes(
+            StaticSizedVoxelGridSmall::<u8>::new(&position, &extents).unwrap(),
+        );
+        check_voxel_grid_changes(OctreeVoxelGrid::<u8>::new(&position, &extents));
+        check_voxel_grid_changes(ChunkedVoxelGrid::<u8>::new());
+    }
+
+    #[test]
+    fn backends_report_traversal_cost() {
+        let position = Vec3I {
+            x: -4,
+            y: -4,
+            z: -4,
+        };
+        let extents = Vec3I { x: 8, y: 8, z: 8 };
+
+        check_traversal_cost(DynamicSizedVoxelGrid::<Terrain>::new(&position, &extents));
+        check_traversal_cost(
+            StaticSizedVoxelGridSmall::<Terrain>::new(&position, &extents).unwrap(),
+        );
+        check_traversal_cost(OctreeVoxelGrid::<Terrain>::new(&position, &extents));
+        check_traversal_cost(ChunkedVoxelGrid::<Terrain>::new());
+    }
+
+    #[test]
+    fn region_edits_are_all_or_nothing() {
+        let position = Vec3I { x: 0, y: 0, z: 0 };
+        let extents = Vec3I { x: 4, y: 4, z: 4 };
+        let mut grid = DynamicSizedVoxelGrid::<u8>::new(&position, &extents);
+
+        let region = CuboidI {
+            position: Vec3I { x: 2, y: 2, z: 2 },
+            extents: Vec3I { x: 3, y: 1, z: 1 },
+        };
+        assert!(!grid.fill_region(&region, &1));
+        assert_eq!(grid.iter().count(), 0);
+
+        let buffer = VoxelRegion::new(&Vec3I { x: 2, y: 2, z: 2 });
+        assert!(!grid.paste_region(&buffer, &Vec3I { x: 3, y: 0, z: 0 }));
+        assert!(grid.paste_region(&buffer, &Vec3I { x: 2, y: 0, z: 0 }));
+    }
 }
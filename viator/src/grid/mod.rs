@@ -1,2 +1,5 @@
+pub mod change;
+#[allow(clippy::module_inception)]
 pub mod grid;
+pub mod region;
 pub mod voxel;
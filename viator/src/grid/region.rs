@@ -0,0 +1,67 @@
+use crate::primitive::{cuboid::CuboidI, vector::Vec3I};
+
+// Standalone copy of a cuboid region of a voxel grid, produced by VoxelGrid::copy_region and
+// consumed by VoxelGrid::paste_region. Cells are addressed in coordinates local to the region,
+// empty cells are kept so a paste reproduces the region exactly.
+
+#[derive(Debug, Clone)]
+pub struct VoxelRegion<T: Copy + Clone + Default + Send + Sync> {
+    extents: Vec3I,
+    cells: Vec<Option<T>>,
+}
+
+impl<T: Copy + Clone + Default + Send + Sync> VoxelRegion<T> {
+    pub fn new(extents: &Vec3I) -> Self {
+        let region = Self::local_bounds(extents);
+
+        Self {
+            extents: region.extents,
+            cells: vec![None; region.volume()],
+        }
+    }
+
+    pub fn extents(&self) -> Vec3I {
+        self.extents
+    }
+
+    pub fn get(&self, local: &Vec3I) -> Option<&T> {
+        let index = self.index(local)?;
+        self.cells[index].as_ref()
+    }
+
+    // Sets the cell at local, returns false if local is outside of the region.
+    pub fn set(&mut self, local: &Vec3I, value: Option<T>) -> bool {
+        match self.index(local) {
+            Some(index) => {
+                self.cells[index] = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Iterates every cell of the region in local coordinates, including the empty ones.
+    pub fn iter(&self) -> impl Iterator<Item = (Vec3I, Option<&T>)> {
+        let bounds = Self::local_bounds(&self.extents);
+
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(index, value)| (bounds.cell_at(index), value.as_ref()))
+    }
+
+    fn local_bounds(extents: &Vec3I) -> CuboidI {
+        CuboidI {
+            position: Vec3I { x: 0, y: 0, z: 0 },
+            extents: Vec3I {
+                x: extents.x.max(0),
+                y: extents.y.max(0),
+                z: extents.z.max(0),
+            },
+        }
+    }
+
+    fn index(&self, local: &Vec3I) -> Option<usize> {
+        Self::local_bounds(&self.extents).cell_index(local)
+    }
+}
@@ -0,0 +1,381 @@
+use std::collections::HashMap;
+
+use crate::{
+    grid::{
+        change::{ChangeLog, GridChangeKind},
+        grid::{PathfindingGrid, VoxelGrid},
+        voxel::occupancy::Occupancy,
+    },
+    path::{
+        cost::{cell_traversal_cost, Traversable},
+        query::PathQuery,
+        result::{PathError, PathResult},
+    },
+    primitive::{
+        alias::{TransformFloat, TransformInt},
+        cuboid::CuboidI,
+        vector::{Vec3F, Vec3I},
+    },
+};
+
+// Example unbounded sparse grid implementation. Cells are stored in fixed size cubic chunks kept
+// in a hash map keyed by chunk coordinate, chunks are allocated on the first write into them and
+// freed again once their last cell is deleted.
+
+// Cell coordinates passed to the grid are world coordinates and may be negative, the grid has no
+// bounds other than the range of TransformInt.
+
+pub const CHUNK_SIZE: TransformInt = 16;
+const CHUNK_VOLUME: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+// Cells of a chunk in local coordinates, its indices are the storage order of the chunk.
+const CHUNK_CELLS: CuboidI = CuboidI {
+    position: Vec3I { x: 0, y: 0, z: 0 },
+    extents: Vec3I {
+        x: CHUNK_SIZE,
+        y: CHUNK_SIZE,
+        z: CHUNK_SIZE,
+    },
+};
+
+#[derive(Debug)]
+struct Chunk<T: Copy + Clone + Default + Send + Sync> {
+    grid: Box<[T]>,
+    occupied: Occupancy,
+}
+
+impl<T: Copy + Clone + Default + Send + Sync> Chunk<T> {
+    fn new() -> Self {
+        Self {
+            grid: vec![T::default(); CHUNK_VOLUME].into_boxed_slice(),
+            occupied: Occupancy::new(CHUNK_VOLUME),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.occupied.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub struct ChunkedVoxelGrid<T: Copy + Clone + Default + Send + Sync> {
+    chunks: HashMap<Vec3I, Chunk<T>>,
+    position: Vec3I,
+    changes: Option<ChangeLog>,
+}
+
+impl<T: Copy + Clone + Default + Send + Sync> Default for ChunkedVoxelGrid<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Copy + Clone + Default + Send + Sync> ChunkedVoxelGrid<T> {
+    pub fn new() -> Self {
+        Self {
+            chunks: HashMap::new(),
+            position: Vec3I { x: 0, y: 0, z: 0 },
+            changes: None,
+        }
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Vec3I, &mut T)> + '_ {
+        self.chunks.iter_mut().flat_map(|(chunk_coord, chunk)| {
+            let chunk_coord = *chunk_coord;
+            chunk
+                .occupied
+                .iter_values_mut(&mut chunk.grid)
+                .map(move |(index, value)| (Self::index_3d(&chunk_coord, index), value))
+        })
+    }
+
+    // Splits world coordinates into the coordinate of the chunk containing the cell and the index
+    // of the cell inside of that chunk.
+    fn index_chunk(x: TransformInt, y: TransformInt, z: TransformInt) -> (Vec3I, usize) {
+        let chunk_coord = Vec3I {
+            x: x.div_euclid(CHUNK_SIZE),
+            y: y.div_euclid(CHUNK_SIZE),
+            z: z.div_euclid(CHUNK_SIZE),
+        };
+        let local = Vec3I {
+            x: x.rem_euclid(CHUNK_SIZE),
+            y: y.rem_euclid(CHUNK_SIZE),
+            z: z.rem_euclid(CHUNK_SIZE),
+        };
+
+        let index = CHUNK_CELLS
+            .cell_index(&local)
+            .expect("local coordinates are inside of the chunk");
+        (chunk_coord, index)
+    }
+
+    fn chunk_bounds(chunk_coord: &Vec3I) -> CuboidI {
+        CuboidI {
+            position: Vec3I {
+                x: chunk_coord.x * CHUNK_SIZE,
+                y: chunk_coord.y * CHUNK_SIZE,
+                z: chunk_coord.z * CHUNK_SIZE,
+            },
+            extents: Vec3I {
+                x: CHUNK_SIZE,
+                y: CHUNK_SIZE,
+                z: CHUNK_SIZE,
+            },
+        }
+    }
+
+    // Converts a chunk coordinate and an index inside of that chunk back into world coordinates.
+    fn index_3d(chunk_coord: &Vec3I, index: usize) -> Vec3I {
+        let local = CHUNK_CELLS.cell_at(index);
+
+        Vec3I {
+            x: chunk_coord.x * CHUNK_SIZE + local.x,
+            y: chunk_coord.y * CHUNK_SIZE + local.y,
+            z: chunk_coord.z * CHUNK_SIZE + local.z,
+        }
+    }
+
+    fn as_cuboid_cells(&self) -> Vec<CuboidI> {
+        let mut cells = Vec::new();
+
+        for (chunk_coord, chunk) in self.chunks.iter() {
+            for idx in chunk.occupied.iter() {
+                let cell = CuboidI {
+                    position: Self::index_3d(chunk_coord, idx),
+                    extents: Vec3I { x: 1, y: 1, z: 1 },
+                };
+                cells.push(cell);
+            }
+        }
+
+        cells
+    }
+}
+
+impl<T: Copy + Clone + Default + Send + Sync> VoxelGrid<T> for ChunkedVoxelGrid<T> {
+    fn contains_cell(&self, _x: TransformInt, _y: TransformInt, _z: TransformInt) -> bool {
+        true
+    }
+
+    fn create_cell(
+        &mut self,
+        x: TransformInt,
+        y: TransformInt,
+        z: TransformInt,
+        value: &T,
+    ) -> bool {
+        let (chunk_coord, index) = Self::index_chunk(x, y, z);
+        let chunk = self.chunks.entry(chunk_coord).or_insert_with(Chunk::new);
+        if !chunk.occupied.contains(index) {
+            chunk.grid[index] = *value;
+            chunk.occupied.insert(index);
+            if let Some(changes) = &mut self.changes {
+                changes.record_cell(GridChangeKind::Created, x, y, z);
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    fn read_cell(&self, x: TransformInt, y: TransformInt, z: TransformInt) -> Option<&T> {
+        let (chunk_coord, index) = Self::index_chunk(x, y, z);
+        let chunk = self.chunks.get(&chunk_coord)?;
+        if chunk.occupied.contains(index) {
+            Some(&chunk.grid[index])
+        } else {
+            None
+        }
+    }
+
+    fn update_cell(
+        &mut self,
+        x: TransformInt,
+        y: TransformInt,
+        z: TransformInt,
+        value: &T,
+    ) -> bool {
+        let (chunk_coord, index) = Self::index_chunk(x, y, z);
+        let Some(chunk) = self.chunks.get_mut(&chunk_coord) else {
+            return false;
+        };
+        if chunk.occupied.contains(index) {
+            chunk.grid[index] = *value;
+            if let Some(changes) = &mut self.changes {
+                changes.record_cell(GridChangeKind::Updated, x, y, z);
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    fn upsert_cell(&mut self, x: TransformInt, y: TransformInt, z: TransformInt, value: &T) {
+        let (chunk_coord, index) = Self::index_chunk(x, y, z);
+        let chunk = self.chunks.entry(chunk_coord).or_insert_with(Chunk::new);
+        chunk.grid[index] = *value;
+        let created = chunk.occupied.insert(index);
+
+        if let Some(changes) = &mut self.changes {
+            let kind = if created {
+                GridChangeKind::Created
+            } else {
+                GridChangeKind::Updated
+            };
+            changes.record_cell(kind, x, y, z);
+        }
+    }
+
+    fn delete_cell_pop(&mut self, x: TransformInt, y: TransformInt, z: TransformInt) -> Option<T> {
+        let (chunk_coord, index) = Self::index_chunk(x, y, z);
+        let chunk = self.chunks.get_mut(&chunk_coord)?;
+        if !chunk.occupied.remove(index) {
+            return None;
+        }
+
+        let value = std::mem::take(&mut chunk.grid[index]);
+        if chunk.is_empty() {
+            self.chunks.remove(&chunk_coord);
+        }
+
+        if let Some(changes) = &mut self.changes {
+            changes.record_cell(GridChangeKind::Deleted, x, y, z);
+        }
+
+        Some(value)
+    }
+
+    fn change_log(&mut self) -> &mut Option<ChangeLog> {
+        &mut self.changes
+    }
+
+    fn iter<'a>(&'a self) -> impl Iterator<Item = (Vec3I, &'a T)>
+    where
+        T: 'a,
+    {
+        self.chunks.iter().flat_map(|(chunk_coord, chunk)| {
+            chunk
+                .occupied
+                .iter()
+                .map(move |index| (Self::index_3d(chunk_coord, index), &chunk.grid[index]))
+        })
+    }
+
+    // Only visits the allocated chunks overlapping region instead of every cell of region.
+    fn iter_region<'a>(&'a self, region: &CuboidI) -> impl Iterator<Item = (Vec3I, &'a T)>
+    where
+        T: 'a,
+    {
+        let region = *region;
+
+        self.chunks
+            .iter()
+            .filter(move |(chunk_coord, _)| {
+                Self::chunk_bounds(chunk_coord)
+                    .intersection(&region)
+                    .is_some()
+            })
+            .flat_map(move |(chunk_coord, chunk)| {
+                chunk.occupied.iter().filter_map(move |index| {
+                    let position = Self::index_3d(chunk_coord, index);
+                    if region.contains(&position) {
+                        Some((position, &chunk.grid[index]))
+                    } else {
+                        None
+                    }
+                })
+            })
+    }
+}
+
+impl<T: Copy + Clone + Default + Send + Sync + Traversable> PathfindingGrid
+    for ChunkedVoxelGrid<T>
+{
+    fn traversal_cost_i(&self, position: &Vec3I) -> Option<f32> {
+        cell_traversal_cost(self.read_cell(position.x, position.y, position.z))
+    }
+
+    fn position_i(&self) -> Vec3I {
+        self.position
+    }
+
+    fn position_f(&self) -> Vec3F {
+        let pos_i = self.position;
+
+        Vec3F {
+            x: pos_i.x as TransformFloat,
+            y: pos_i.y as TransformFloat,
+            z: pos_i.z as TransformFloat,
+        }
+    }
+
+    fn as_cuboid_cells(&self) -> Vec<CuboidI> {
+        self.as_cuboid_cells()
+    }
+
+    fn compute_path_i(&self, query: &PathQuery) -> Result<PathResult, PathError> {
+        query.engine.compute_path_i(self, query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitive::cell::Cell;
+
+    #[test]
+    fn allocates_and_frees_chunks() {
+        let mut grid = ChunkedVoxelGrid::<Cell>::new();
+
+        assert!(grid.create_cell(-1, -1, -1, &Cell));
+        assert!(grid.create_cell(0, 0, 0, &Cell));
+        assert!(grid.create_cell(1, 0, 0, &Cell));
+        assert_eq!(grid.chunk_count(), 2);
+        assert!(grid.read_cell(-1, -1, -1).is_some());
+
+        assert!(grid.delete_cell_check(-1, -1, -1));
+        assert_eq!(grid.chunk_count(), 1);
+        grid.delete_cell(0, 0, 0);
+        assert_eq!(grid.chunk_count(), 1);
+        grid.delete_cell(1, 0, 0);
+        assert_eq!(grid.chunk_count(), 0);
+    }
+
+    #[test]
+    fn converts_between_world_and_chunk_coordinates() {
+        let mut grid = ChunkedVoxelGrid::<Cell>::new();
+        let positions = [
+            Vec3I {
+                x: -17,
+                y: 3,
+                z: 40,
+            },
+            Vec3I {
+                x: 15,
+                y: -16,
+                z: 0,
+            },
+            Vec3I {
+                x: 1_000,
+                y: -1_000,
+                z: 16,
+            },
+        ];
+
+        for position in positions.iter() {
+            grid.upsert_cell(position.x, position.y, position.z, &Cell);
+        }
+
+        let mut cells: Vec<Vec3I> = grid
+            .as_cuboid_cells()
+            .into_iter()
+            .map(|cell| cell.position)
+            .collect();
+        cells.sort_by_key(|cell| (cell.x, cell.y, cell.z));
+
+        assert_eq!(cells, positions.to_vec());
+    }
+}
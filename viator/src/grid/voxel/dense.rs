@@ -0,0 +1,173 @@
+use std::ops::DerefMut;
+
+use crate::{
+    grid::{
+        change::{ChangeLog, GridChangeKind},
+        voxel::{local, occupancy::Occupancy},
+    },
+    primitive::{alias::TransformInt, cuboid::CuboidI, vector::Vec3I},
+};
+
+// Cells of the voxel grids storing them in a dense array in the order of CuboidI::cell_at, along
+// with the set of occupied slots and the change log. The backends only allocate the slots, every
+// slot of a cell has to exist before the cell is written.
+
+#[derive(Debug)]
+pub struct DenseCells<S> {
+    bounds: CuboidI,
+    slots: S,
+    occupied: Occupancy,
+    changes: Option<ChangeLog>,
+}
+
+impl<T: Copy + Default, S: DerefMut<Target = [T]>> DenseCells<S> {
+    pub fn new(bounds: &CuboidI, slots: S) -> Self {
+        Self {
+            bounds: *bounds,
+            slots,
+            occupied: Occupancy::new(bounds.volume()),
+            changes: None,
+        }
+    }
+
+    pub fn bounds(&self) -> &CuboidI {
+        &self.bounds
+    }
+
+    pub fn slots_mut(&mut self) -> &mut S {
+        &mut self.slots
+    }
+
+    pub fn changes_mut(&mut self) -> &mut Option<ChangeLog> {
+        &mut self.changes
+    }
+
+    // Converts world coordinates into an index into the slots, returns None when the coordinates
+    // are outside of the bounds.
+    pub fn index(&self, x: TransformInt, y: TransformInt, z: TransformInt) -> Option<usize> {
+        let extents = &self.bounds.extents;
+        let (width, height, depth) = (extents.x as usize, extents.y as usize, extents.z as usize);
+        let local = local::local_in_bounds(
+            &self.bounds.position,
+            &Vec3I { x, y, z },
+            width,
+            height,
+            depth,
+        )?;
+
+        Some(local::local_index(&local, width, height, depth))
+    }
+
+    pub fn create(&mut self, x: TransformInt, y: TransformInt, z: TransformInt, value: &T) -> bool {
+        let Some(index) = self.index(x, y, z) else {
+            return false;
+        };
+        if !self.occupied.insert(index) {
+            return false;
+        }
+
+        self.slots[index] = *value;
+        self.record(GridChangeKind::Created, x, y, z);
+        true
+    }
+
+    pub fn read(&self, x: TransformInt, y: TransformInt, z: TransformInt) -> Option<&T> {
+        let index = self.index(x, y, z)?;
+        self.occupied.contains(index).then(|| &self.slots[index])
+    }
+
+    pub fn update(&mut self, x: TransformInt, y: TransformInt, z: TransformInt, value: &T) -> bool {
+        let Some(index) = self.index(x, y, z) else {
+            return false;
+        };
+        if !self.occupied.contains(index) {
+            return false;
+        }
+
+        self.slots[index] = *value;
+        self.record(GridChangeKind::Updated, x, y, z);
+        true
+    }
+
+    pub fn upsert(&mut self, x: TransformInt, y: TransformInt, z: TransformInt, value: &T) {
+        let Some(index) = self.index(x, y, z) else {
+            return;
+        };
+
+        self.slots[index] = *value;
+        let kind = if self.occupied.insert(index) {
+            GridChangeKind::Created
+        } else {
+            GridChangeKind::Updated
+        };
+        self.record(kind, x, y, z);
+    }
+
+    pub fn delete(&mut self, x: TransformInt, y: TransformInt, z: TransformInt) -> Option<T> {
+        let index = self.index(x, y, z)?;
+        if !self.occupied.remove(index) {
+            return None;
+        }
+
+        self.record(GridChangeKind::Deleted, x, y, z);
+        Some(std::mem::take(&mut self.slots[index]))
+    }
+
+    pub fn record(
+        &mut self,
+        kind: GridChangeKind,
+        x: TransformInt,
+        y: TransformInt,
+        z: TransformInt,
+    ) {
+        if let Some(changes) = &mut self.changes {
+            changes.record_cell(kind, x, y, z);
+        }
+    }
+
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (Vec3I, &'a T)>
+    where
+        T: 'a,
+    {
+        self.occupied
+            .iter()
+            .map(move |index| (self.bounds.cell_at(index), &self.slots[index]))
+    }
+
+    pub fn iter_mut<'a>(&'a mut self) -> impl Iterator<Item = (Vec3I, &'a mut T)>
+    where
+        T: 'a,
+    {
+        let bounds = self.bounds;
+        self.occupied
+            .iter_values_mut(&mut self.slots)
+            .map(move |(index, value)| (bounds.cell_at(index), value))
+    }
+
+    pub fn iter_region<'a>(&'a self, region: &CuboidI) -> impl Iterator<Item = (Vec3I, &'a T)>
+    where
+        T: 'a,
+    {
+        let region = self.bounds.intersection(region);
+
+        region
+            .into_iter()
+            .flat_map(|region| region.iter_cells())
+            .filter_map(move |position| {
+                let index = self.bounds.cell_index(&position)?;
+                self.occupied
+                    .contains(index)
+                    .then(|| (position, &self.slots[index]))
+            })
+    }
+
+    pub fn as_cuboid_cells(&self) -> Vec<CuboidI> {
+        self.occupied
+            .iter()
+            .map(|index| CuboidI {
+                position: self.bounds.cell_at(index),
+                extents: Vec3I { x: 1, y: 1, z: 1 },
+            })
+            .collect()
+    }
+}
@@ -1,8 +1,14 @@
-use std::collections::HashSet;
-
 use crate::{
-    grid::grid::PathfindingGrid,
-    path::jps::jps_i,
+    grid::{
+        change::{ChangeLog, GridChangeKind},
+        grid::{PathfindingGrid, VoxelGrid},
+        voxel::{dense::DenseCells, local},
+    },
+    path::{
+        cost::{cell_traversal_cost, Traversable},
+        query::PathQuery,
+        result::{PathError, PathResult},
+    },
     primitive::{
         alias::{TransformFloat, TransformInt},
         cuboid::CuboidI,
@@ -13,222 +19,219 @@ use crate::{
 // Example dynamically sized grid implementation that supports any size up to the max value of
 // TransformInt.
 
-// ToDo: Add support for negative coordinates, currently grid will crash if operations are
-//       performed on negative coordinates
+// Cell coordinates passed to the grid are world coordinates, the grid covers the cells from
+// position (inclusive) to position + extents (exclusive) on every axis. Coordinates may be
+// negative, operations on cells outside of the grid bounds are rejected. The bounds can be changed
+// after construction with resize, grow_to_include and set_origin, cells keep their world
+// coordinates when the bounds change.
 
 #[derive(Debug)]
 pub struct DynamicSizedVoxelGrid<T: Copy + Clone + Default + Send + Sync> {
-    width: usize,
-    height: usize,
-    depth: usize,
-    grid: Vec<T>,
-    occupied: HashSet<usize>,
-    position: Vec3I,
+    // Slots are only allocated up to the highest index written so far.
+    cells: DenseCells<Vec<T>>,
 }
 
 impl<T: Copy + Clone + Default + Send + Sync> DynamicSizedVoxelGrid<T> {
     pub fn new(position: &Vec3I, extents: &Vec3I) -> Self {
-        let size = (extents.x * extents.y * extents.z) as usize;
-
-        let width = extents.x as usize;
-        let height = extents.y as usize;
-        let depth = extents.z as usize;
+        let bounds = CuboidI {
+            position: *position,
+            extents: Vec3I {
+                x: extents.x.max(0),
+                y: extents.y.max(0),
+                z: extents.z.max(0),
+            },
+        };
 
         Self {
-            width,
-            height,
-            depth,
-            grid: Vec::with_capacity(size),
-            occupied: HashSet::new(),
-            position: position.clone(),
+            cells: DenseCells::new(&bounds, Vec::with_capacity(bounds.volume())),
         }
     }
 
     pub fn bounds_as_cuboid(&self) -> CuboidI {
-        CuboidI {
-            position: self.position.clone(),
-            extents: Vec3I {
-                x: self.width as TransformInt,
-                y: self.height as TransformInt,
-                z: self.depth as TransformInt,
-            },
-        }
+        *self.cells.bounds()
     }
 
-    pub fn create_cell(
-        &mut self,
-        x: TransformInt,
-        y: TransformInt,
-        z: TransformInt,
-        value: &T,
-    ) -> bool {
-        let index = self.index_1d(x, y, z);
-        self.ensure_capacity(index);
-        if !self.occupied.contains(&index) {
-            self.grid[index] = value.clone();
-            self.occupied.insert(index);
-            true
-        } else {
-            false
-        }
+    // None when world is too far from the grid for the local coordinates to fit TransformInt.
+    pub fn world_to_local(&self, world: &Vec3I) -> Option<Vec3I> {
+        local::world_to_local(&self.cells.bounds().position, world)
     }
 
-    pub fn read_cell(&self, x: TransformInt, y: TransformInt, z: TransformInt) -> Option<&T> {
-        let index = self.index_1d(x, y, z);
-        if self.occupied.contains(&index) {
-            Some(&self.grid[index])
-        } else {
-            None
-        }
+    pub fn local_to_world(&self, local: &Vec3I) -> Vec3I {
+        local::local_to_world(&self.cells.bounds().position, local)
     }
 
-    pub fn read_cell_cloned(&self, x: TransformInt, y: TransformInt, z: TransformInt) -> Option<T> {
-        self.read_cell(x, y, z).cloned()
+    // Changes the extents of the grid while keeping its position, returns the cells that no longer
+    // fit inside of the new bounds.
+    pub fn resize(&mut self, extents: &Vec3I) -> Vec<(Vec3I, T)> {
+        let position = self.cells.bounds().position;
+        self.rebuild(&position, extents)
     }
 
-    pub fn update_cell(
-        &mut self,
-        x: TransformInt,
-        y: TransformInt,
-        z: TransformInt,
-        value: &T,
-    ) -> bool {
-        let index = self.index_1d(x, y, z);
-        if self.occupied.contains(&index) {
-            self.grid[index] = value.clone();
-            true
-        } else {
-            false
+    // Moves the grid to a new position while keeping its extents, returns the cells that no
+    // longer fit inside of the new bounds.
+    pub fn set_origin(&mut self, position: &Vec3I) -> Vec<(Vec3I, T)> {
+        let extents = self.bounds_as_cuboid().extents;
+        self.rebuild(position, &extents)
+    }
+
+    // Grows the grid by the minimum amount needed for position to fit inside of the bounds, no
+    // cells are dropped. Returns false if position was already inside of the bounds.
+    pub fn grow_to_include(&mut self, position: &Vec3I) -> bool {
+        let bounds = self.bounds_as_cuboid();
+        if bounds.contains(position) {
+            return false;
         }
+
+        let (min, max) = if bounds.is_empty() {
+            (*position, *position)
+        } else {
+            let max = Vec3I {
+                x: bounds.position.x + bounds.extents.x - 1,
+                y: bounds.position.y + bounds.extents.y - 1,
+                z: bounds.position.z + bounds.extents.z - 1,
+            };
+            (
+                Vec3I {
+                    x: bounds.position.x.min(position.x),
+                    y: bounds.position.y.min(position.y),
+                    z: bounds.position.z.min(position.z),
+                },
+                Vec3I {
+                    x: max.x.max(position.x),
+                    y: max.y.max(position.y),
+                    z: max.z.max(position.z),
+                },
+            )
+        };
+
+        let extents = Vec3I {
+            x: max.x - min.x + 1,
+            y: max.y - min.y + 1,
+            z: max.z - min.z + 1,
+        };
+        self.rebuild(&min, &extents);
+
+        true
     }
 
-    pub fn upsert_cell(&mut self, x: TransformInt, y: TransformInt, z: TransformInt, value: &T) {
-        let index = self.index_1d(x, y, z);
-        self.ensure_capacity(index);
-        self.grid[index] = value.clone();
-        self.occupied.insert(index);
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Vec3I, &mut T)> + '_ {
+        self.cells.iter_mut()
     }
 
-    pub fn delete_cell(&mut self, x: TransformInt, y: TransformInt, z: TransformInt) {
-        let index = self.index_1d(x, y, z);
-        if self.occupied.remove(&index) {
-            self.grid[index] = T::default();
+    // Replaces the bounds of the grid and re-indexes every cell, returns the cells that are
+    // outside of the new bounds.
+    fn rebuild(&mut self, position: &Vec3I, extents: &Vec3I) -> Vec<(Vec3I, T)> {
+        let changes = self.cells.changes_mut().take();
+        let previous = std::mem::replace(self, Self::new(position, extents));
+        let mut dropped = Vec::new();
+
+        // Cells that stayed inside of the bounds keep their world coordinates, only the dropped
+        // cells are visible as changes.
+        for (cell_position, value) in previous.cells.iter() {
+            let (x, y, z) = (cell_position.x, cell_position.y, cell_position.z);
+            self.ensure_capacity(x, y, z);
+            if !self.cells.create(x, y, z, value) {
+                dropped.push((cell_position, *value));
+            }
         }
+
+        *self.cells.changes_mut() = changes;
+        for (cell_position, _) in dropped.iter() {
+            self.cells.record(
+                GridChangeKind::Deleted,
+                cell_position.x,
+                cell_position.y,
+                cell_position.z,
+            );
+        }
+
+        dropped
     }
 
-    pub fn delete_cell_check(&mut self, x: TransformInt, y: TransformInt, z: TransformInt) -> bool {
-        let index = self.index_1d(x, y, z);
-        if self.occupied.remove(&index) {
-            self.grid[index] = T::default();
-            true
-        } else {
-            false
+    // Allocates the slots up to the one of the cell, if it is inside of the bounds.
+    fn ensure_capacity(&mut self, x: TransformInt, y: TransformInt, z: TransformInt) {
+        let Some(index) = self.cells.index(x, y, z) else {
+            return;
+        };
+
+        let slots = self.cells.slots_mut();
+        if index >= slots.len() {
+            slots.resize(index + 1, T::default());
         }
     }
+}
+
+impl<T: Copy + Clone + Default + Send + Sync> VoxelGrid<T> for DynamicSizedVoxelGrid<T> {
+    fn contains_cell(&self, x: TransformInt, y: TransformInt, z: TransformInt) -> bool {
+        self.cells.index(x, y, z).is_some()
+    }
 
-    pub fn delete_cell_pop(
+    fn create_cell(
         &mut self,
         x: TransformInt,
         y: TransformInt,
         z: TransformInt,
-    ) -> Option<T> {
-        let index = self.index_1d(x, y, z);
-        if self.occupied.remove(&index) {
-            Some(std::mem::replace(&mut self.grid[index], T::default()))
-        } else {
-            None
-        }
+        value: &T,
+    ) -> bool {
+        self.ensure_capacity(x, y, z);
+        self.cells.create(x, y, z, value)
     }
 
-    fn ensure_capacity(&mut self, index: usize) {
-        if index >= self.grid.len() {
-            self.grid.resize(index + 1, unsafe { std::mem::zeroed() });
-        }
+    fn read_cell(&self, x: TransformInt, y: TransformInt, z: TransformInt) -> Option<&T> {
+        self.cells.read(x, y, z)
     }
 
-    fn index_1d(&self, x: TransformInt, y: TransformInt, z: TransformInt) -> usize {
-        let self_pos = self.position;
-
-        #[cfg(feature = "y_up")]
-        {
-            // X, Z, Y
-            ((x + self_pos.x)
-                + ((z + self_pos.z) * self.width as TransformInt)
-                + ((y + self_pos.y) * self.width as TransformInt * self.depth as TransformInt))
-                as usize
-        }
-
-        #[cfg(feature = "z_up")]
-        {
-            // X, Y, Z
-            ((x + self_pos.x)
-                + ((y + self_pos.y) * self.width as TransformInt)
-                + ((z + self_pos.z) * self.width as TransformInt * self.depth as TransformInt))
-                as usize
-        }
+    fn update_cell(
+        &mut self,
+        x: TransformInt,
+        y: TransformInt,
+        z: TransformInt,
+        value: &T,
+    ) -> bool {
+        self.cells.update(x, y, z, value)
     }
 
-    fn index_3d(&self, index: usize) -> Vec3I {
-        let index = index as TransformInt;
-        let width = self.width as TransformInt;
-        let depth = self.depth as TransformInt;
-
-        let x: TransformInt;
-        let y: TransformInt;
-        let z: TransformInt;
-
-        #[cfg(feature = "y_up")]
-        {
-            x = index % width;
-            z = (index / width) % depth;
-            y = index / (width * depth);
-        }
-
-        #[cfg(feature = "z_up")]
-        {
-            x = index % width;
-            y = (index / width) % depth;
-            z = index / (width * depth);
-        }
-
-        Vec3I { x, y, z }
+    fn upsert_cell(&mut self, x: TransformInt, y: TransformInt, z: TransformInt, value: &T) {
+        self.ensure_capacity(x, y, z);
+        self.cells.upsert(x, y, z, value);
     }
 
-    fn as_cuboid_cells(&self) -> Vec<CuboidI> {
-        let mut cells = Vec::with_capacity(self.grid.len());
-
-        for (idx, _) in self.grid.iter().enumerate() {
-            if !self.occupied.contains(&idx) {
-                continue;
-            }
+    fn delete_cell_pop(&mut self, x: TransformInt, y: TransformInt, z: TransformInt) -> Option<T> {
+        self.cells.delete(x, y, z)
+    }
 
-            let cell = CuboidI {
-                position: self.index_3d(idx),
-                extents: Vec3I { x: 1, y: 1, z: 1 },
-            };
-            cells.push(cell);
-        }
+    fn change_log(&mut self) -> &mut Option<ChangeLog> {
+        self.cells.changes_mut()
+    }
 
-        cells
+    fn iter<'a>(&'a self) -> impl Iterator<Item = (Vec3I, &'a T)>
+    where
+        T: 'a,
+    {
+        self.cells.iter()
     }
 
-    fn is_valid_path_node_i(&self, position: &Vec3I) -> bool {
-        let index = self.index_1d(position.x, position.y, position.z);
-        self.occupied.contains(&index)
+    fn iter_region<'a>(&'a self, region: &CuboidI) -> impl Iterator<Item = (Vec3I, &'a T)>
+    where
+        T: 'a,
+    {
+        self.cells.iter_region(region)
     }
 }
 
-impl<T: Copy + Clone + Default + Send + Sync> PathfindingGrid for DynamicSizedVoxelGrid<T> {
-    fn is_valid_path_node_i(&self, position: &Vec3I) -> bool {
-        self.is_valid_path_node_i(position)
+impl<T: Copy + Clone + Default + Send + Sync + Traversable> PathfindingGrid
+    for DynamicSizedVoxelGrid<T>
+{
+    fn traversal_cost_i(&self, position: &Vec3I) -> Option<f32> {
+        cell_traversal_cost(self.read_cell(position.x, position.y, position.z))
     }
 
     fn position_i(&self) -> Vec3I {
-        self.position
+        self.cells.bounds().position
     }
 
     fn position_f(&self) -> crate::primitive::vector::Vec3F {
-        let pos_i = self.position.clone();
+        let pos_i = self.cells.bounds().position;
 
         Vec3F {
             x: pos_i.x as TransformFloat,
@@ -238,17 +241,131 @@ impl<T: Copy + Clone + Default + Send + Sync> PathfindingGrid for DynamicSizedVo
     }
 
     fn as_cuboid_cells(&self) -> Vec<CuboidI> {
-        self.as_cuboid_cells()
+        self.cells.as_cuboid_cells()
+    }
+
+    fn compute_path_i(&self, query: &PathQuery) -> Result<PathResult, PathError> {
+        query.engine.compute_path_i(self, query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitive::cell::Cell;
+
+    #[test]
+    fn accepts_negative_coordinates() {
+        let position = Vec3I {
+            x: -5,
+            y: -5,
+            z: -5,
+        };
+        let extents = Vec3I {
+            x: 10,
+            y: 10,
+            z: 10,
+        };
+        let mut grid = DynamicSizedVoxelGrid::<Cell>::new(&position, &extents);
+
+        assert!(grid.create_cell(-5, -5, -5, &Cell));
+        assert!(grid.create_cell(4, 4, 4, &Cell));
+        assert!(grid.read_cell(-5, -5, -5).is_some());
+        assert!(grid.read_cell(4, 4, 4).is_some());
+
+        let cells: Vec<Vec3I> = grid
+            .as_cuboid_cells()
+            .into_iter()
+            .map(|cell| cell.position)
+            .collect();
+        assert_eq!(
+            cells,
+            vec![
+                Vec3I {
+                    x: -5,
+                    y: -5,
+                    z: -5
+                },
+                Vec3I { x: 4, y: 4, z: 4 }
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_out_of_bounds_coordinates() {
+        let position = Vec3I { x: 0, y: 0, z: 0 };
+        let extents = Vec3I { x: 4, y: 4, z: 4 };
+        let mut grid = DynamicSizedVoxelGrid::<Cell>::new(&position, &extents);
+
+        assert!(!grid.create_cell(4, 0, 0, &Cell));
+        assert!(!grid.create_cell(-1, 0, 0, &Cell));
+        assert!(!grid.create_cell(0, 0, 4, &Cell));
+        assert!(grid.read_cell(0, 1, 0).is_none());
+        assert!(!grid.is_valid_path_node_i(&Vec3I { x: 0, y: 1, z: 0 }));
+
+        // Coordinates too far from the origin for the local coordinates to fit.
+        let mut grid = DynamicSizedVoxelGrid::<Cell>::new(&Vec3I { x: 1, y: 1, z: 1 }, &extents);
+        assert!(!grid.create_cell(TransformInt::MIN, 1, 1, &Cell));
+        assert!(grid.read_cell(1, 1, TransformInt::MIN).is_none());
+        assert_eq!(
+            grid.world_to_local(&Vec3I {
+                x: TransformInt::MIN,
+                y: 1,
+                z: 1
+            }),
+            None
+        );
     }
 
-    fn compute_path_i(
-        &self,
-        start: &Vec3I,
-        goal: &Vec3I,
-        pathfinding_engine: &crate::path::path::PathfindingType,
-    ) -> Option<Vec<Vec3I>> {
-        match pathfinding_engine {
-            crate::path::path::PathfindingType::Jps => jps_i(start, goal, self),
+    #[test]
+    fn iter_mut_updates_occupied_cells() {
+        let position = Vec3I {
+            x: -2,
+            y: -2,
+            z: -2,
+        };
+        let extents = Vec3I { x: 4, y: 4, z: 4 };
+        let mut grid = DynamicSizedVoxelGrid::<u8>::new(&position, &extents);
+
+        grid.upsert_cell(-2, -2, -2, &1);
+        grid.upsert_cell(1, 0, -1, &2);
+        grid.upsert_cell(1, 1, 1, &3);
+
+        for (position, value) in grid.iter_mut() {
+            *value += position.x as u8;
         }
+
+        assert_eq!(grid.read_cell_cloned(-2, -2, -2), Some(255));
+        assert_eq!(grid.read_cell_cloned(1, 0, -1), Some(3));
+        assert_eq!(grid.read_cell_cloned(1, 1, 1), Some(4));
+    }
+
+    #[test]
+    fn rebounding_keeps_world_coordinates() {
+        let position = Vec3I { x: 0, y: 0, z: 0 };
+        let extents = Vec3I { x: 4, y: 4, z: 4 };
+        let mut grid = DynamicSizedVoxelGrid::<u8>::new(&position, &extents);
+
+        grid.upsert_cell(0, 0, 0, &1);
+        grid.upsert_cell(3, 3, 3, &2);
+
+        assert!(grid.grow_to_include(&Vec3I { x: -2, y: 5, z: 0 }));
+        assert!(!grid.grow_to_include(&Vec3I { x: 0, y: 0, z: 0 }));
+        assert_eq!(
+            grid.bounds_as_cuboid().position,
+            Vec3I { x: -2, y: 0, z: 0 }
+        );
+        assert_eq!(grid.bounds_as_cuboid().extents, Vec3I { x: 6, y: 6, z: 4 });
+        assert_eq!(grid.read_cell_cloned(0, 0, 0), Some(1));
+        assert_eq!(grid.read_cell_cloned(3, 3, 3), Some(2));
+        assert!(grid.create_cell(-2, 5, 0, &3));
+
+        let dropped = grid.set_origin(&Vec3I { x: -2, y: 1, z: 0 });
+        assert_eq!(dropped, vec![(Vec3I { x: 0, y: 0, z: 0 }, 1)]);
+        assert_eq!(grid.read_cell_cloned(-2, 5, 0), Some(3));
+
+        let dropped = grid.resize(&Vec3I { x: 3, y: 6, z: 4 });
+        assert_eq!(dropped, vec![(Vec3I { x: 3, y: 3, z: 3 }, 2)]);
+        assert_eq!(grid.iter().count(), 1);
     }
 }
@@ -0,0 +1,66 @@
+use crate::primitive::vector::Vec3I;
+
+// Conversions between world coordinates and coordinates local to the origin of a bounded grid,
+// shared by the grids storing their cells in a dense array or tree.
+
+// None when the subtraction overflows, such coordinates are outside of every grid.
+pub fn world_to_local(origin: &Vec3I, world: &Vec3I) -> Option<Vec3I> {
+    Some(Vec3I {
+        x: world.x.checked_sub(origin.x)?,
+        y: world.y.checked_sub(origin.y)?,
+        z: world.z.checked_sub(origin.z)?,
+    })
+}
+
+pub fn local_to_world(origin: &Vec3I, local: &Vec3I) -> Vec3I {
+    Vec3I {
+        x: local.x + origin.x,
+        y: local.y + origin.y,
+        z: local.z + origin.z,
+    }
+}
+
+// Converts world coordinates into local coordinates, returns None when the coordinates are
+// outside of a grid of the given size placed at origin.
+pub fn local_in_bounds(
+    origin: &Vec3I,
+    world: &Vec3I,
+    width: usize,
+    height: usize,
+    depth: usize,
+) -> Option<Vec3I> {
+    let local = world_to_local(origin, world)?;
+
+    if local.x < 0
+        || local.y < 0
+        || local.z < 0
+        || local.x as usize >= width
+        || local.y as usize >= height
+        || local.z as usize >= depth
+    {
+        return None;
+    }
+
+    Some(local)
+}
+
+// Index of in bounds local coordinates in the dense storage order of the voxel grids.
+pub fn local_index(local: &Vec3I, width: usize, height: usize, depth: usize) -> usize {
+    let local_x = local.x as usize;
+    let local_y = local.y as usize;
+    let local_z = local.z as usize;
+
+    #[cfg(feature = "y_up")]
+    {
+        // X, Z, Y
+        let _ = height;
+        local_x + (local_z * width) + (local_y * width * depth)
+    }
+
+    #[cfg(feature = "z_up")]
+    {
+        // X, Y, Z
+        let _ = depth;
+        local_x + (local_y * width) + (local_z * width * height)
+    }
+}
@@ -1,2 +1,7 @@
+pub mod chunked_voxel_grid;
+pub mod dense;
 pub mod dynamic_sized_voxel_grid;
+pub mod local;
+pub mod occupancy;
+pub mod octree_voxel_grid;
 pub mod static_sized_voxel_grid;
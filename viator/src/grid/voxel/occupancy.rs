@@ -0,0 +1,169 @@
+// Dense bit packed occupancy set used by the voxel grids to track which slots of their backing
+// storage hold a value. One bit per slot, iteration works on whole 64 bit words so large empty
+// regions are skipped quickly.
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+#[derive(Debug, Clone, Default)]
+pub struct Occupancy {
+    words: Vec<u64>,
+    len: usize,
+    count: usize,
+}
+
+impl Occupancy {
+    pub fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(WORD_BITS)],
+            len,
+            count: 0,
+        }
+    }
+
+    // Number of occupied slots.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        if index >= self.len {
+            return false;
+        }
+
+        self.words[index / WORD_BITS] & (1 << (index % WORD_BITS)) != 0
+    }
+
+    // Marks the slot as occupied, returns true if it was empty before.
+    pub fn insert(&mut self, index: usize) -> bool {
+        debug_assert!(index < self.len, "occupancy index out of range");
+
+        let word = &mut self.words[index / WORD_BITS];
+        let mask = 1 << (index % WORD_BITS);
+
+        if *word & mask != 0 {
+            return false;
+        }
+
+        *word |= mask;
+        self.count += 1;
+        true
+    }
+
+    // Marks the slot as empty, returns true if it was occupied before.
+    pub fn remove(&mut self, index: usize) -> bool {
+        if index >= self.len {
+            return false;
+        }
+
+        let word = &mut self.words[index / WORD_BITS];
+        let mask = 1 << (index % WORD_BITS);
+
+        if *word & mask == 0 {
+            return false;
+        }
+
+        *word &= !mask;
+        self.count -= 1;
+        true
+    }
+
+    // Iterates the indices of all occupied slots in ascending order.
+    pub fn iter(&self) -> OccupancyIter<'_> {
+        self.iter_from(0)
+    }
+
+    // Iterates the indices of all occupied slots at or after start in ascending order.
+    pub fn iter_from(&self, start: usize) -> OccupancyIter<'_> {
+        let word_index = start / WORD_BITS;
+        let current = match self.words.get(word_index) {
+            Some(word) => word & (u64::MAX << (start % WORD_BITS)),
+            None => 0,
+        };
+
+        OccupancyIter {
+            words: &self.words,
+            word_index,
+            current,
+        }
+    }
+
+    // Pairs the index of every occupied slot with a mutable reference to its value in slots, the
+    // backing storage the set tracks.
+    pub fn iter_values_mut<'a, T>(
+        &'a self,
+        slots: &'a mut [T],
+    ) -> impl Iterator<Item = (usize, &'a mut T)> + 'a {
+        let mut slots = slots;
+        let mut offset = 0;
+
+        // Occupied indices are ascending, split each value off the front of the remaining slots.
+        self.iter().map(move |index| {
+            let (_, rest) = std::mem::take(&mut slots).split_at_mut(index - offset);
+            let (value, rest) = rest.split_first_mut().unwrap();
+            slots = rest;
+            offset = index + 1;
+
+            (index, value)
+        })
+    }
+}
+
+pub struct OccupancyIter<'a> {
+    words: &'a [u64],
+    word_index: usize,
+    current: u64,
+}
+
+impl Iterator for OccupancyIter<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.current == 0 {
+            self.word_index += 1;
+            self.current = *self.words.get(self.word_index)?;
+        }
+
+        let bit = self.current.trailing_zeros() as usize;
+        self.current &= self.current - 1;
+
+        Some(self.word_index * WORD_BITS + bit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_occupied_slots() {
+        let mut occupancy = Occupancy::new(200);
+
+        assert!(occupancy.insert(0));
+        assert!(occupancy.insert(63));
+        assert!(occupancy.insert(64));
+        assert!(occupancy.insert(199));
+        assert!(!occupancy.insert(64));
+        assert_eq!(occupancy.count(), 4);
+
+        assert!(occupancy.contains(63));
+        assert!(!occupancy.contains(62));
+        assert!(!occupancy.contains(1_000));
+
+        assert_eq!(
+            occupancy.iter().collect::<Vec<usize>>(),
+            vec![0, 63, 64, 199]
+        );
+        assert_eq!(
+            occupancy.iter_from(64).collect::<Vec<usize>>(),
+            vec![64, 199]
+        );
+
+        assert!(occupancy.remove(63));
+        assert!(!occupancy.remove(63));
+        assert_eq!(occupancy.count(), 3);
+    }
+}
@@ -0,0 +1,686 @@
+use crate::{
+    grid::{
+        change::{ChangeLog, GridChangeKind},
+        grid::{with_region_change, PathfindingGrid, VoxelGrid},
+        voxel::local,
+    },
+    path::{
+        cost::{cell_traversal_cost, Traversable},
+        query::PathQuery,
+        result::{PathError, PathResult},
+    },
+    primitive::{
+        alias::{TransformFloat, TransformInt},
+        cuboid::CuboidI,
+        vector::{Vec3F, Vec3I},
+    },
+};
+
+// Example sparse voxel octree grid implementation. The grid is stored as a cube with a power of
+// two edge length covering the requested extents, regions where every cell holds the same value
+// (or is empty) are collapsed into a single leaf node. Large uniform volumes cost a single node
+// instead of one slot per cell.
+
+// Cell coordinates passed to the grid are world coordinates, the grid covers the cells from
+// position (inclusive) to position + extents (exclusive) on every axis. Coordinates may be
+// negative, operations on cells outside of the grid bounds are rejected.
+
+#[derive(Debug)]
+enum OctreeNode<T: Copy + Clone + Default + PartialEq + Send + Sync> {
+    Leaf(Option<T>),
+    Branch(Box<[OctreeNode<T>; 8]>),
+}
+
+impl<T: Copy + Clone + Default + PartialEq + Send + Sync> OctreeNode<T> {
+    // Returns the index of the child containing local, along with the local origin of that child.
+    fn child_index(origin: &Vec3I, half: TransformInt, local: &Vec3I) -> (usize, Vec3I) {
+        let mut index = 0;
+        let mut child_origin = *origin;
+
+        if local.x >= origin.x + half {
+            index |= 1;
+            child_origin.x += half;
+        }
+        if local.y >= origin.y + half {
+            index |= 2;
+            child_origin.y += half;
+        }
+        if local.z >= origin.z + half {
+            index |= 4;
+            child_origin.z += half;
+        }
+
+        (index, child_origin)
+    }
+
+    // Inverse of child_index, returns the local origin of the child at index.
+    fn child_origin(origin: &Vec3I, half: TransformInt, index: usize) -> Vec3I {
+        Vec3I {
+            x: origin.x + if index & 1 != 0 { half } else { 0 },
+            y: origin.y + if index & 2 != 0 { half } else { 0 },
+            z: origin.z + if index & 4 != 0 { half } else { 0 },
+        }
+    }
+
+    fn get(&self, origin: Vec3I, size: TransformInt, local: &Vec3I) -> Option<&T> {
+        match self {
+            OctreeNode::Leaf(value) => value.as_ref(),
+            OctreeNode::Branch(children) => {
+                let half = size / 2;
+                let (index, child_origin) = Self::child_index(&origin, half, local);
+                children[index].get(child_origin, half, local)
+            }
+        }
+    }
+
+    // Sets the cell at local to value and returns the previous value, collapsing the node back
+    // into a leaf when all of its children end up holding the same value.
+    fn set(
+        &mut self,
+        origin: Vec3I,
+        size: TransformInt,
+        local: &Vec3I,
+        value: Option<T>,
+    ) -> Option<T> {
+        if size == 1 {
+            return match self {
+                OctreeNode::Leaf(previous) => std::mem::replace(previous, value),
+                OctreeNode::Branch(_) => unreachable!("octree branch at unit size"),
+            };
+        }
+
+        if let OctreeNode::Leaf(current) = self {
+            if *current == value {
+                return *current;
+            }
+
+            let current = *current;
+            *self =
+                OctreeNode::Branch(Box::new(std::array::from_fn(|_| OctreeNode::Leaf(current))));
+        }
+
+        let OctreeNode::Branch(children) = self else {
+            unreachable!("octree leaf was split into a branch");
+        };
+
+        let half = size / 2;
+        let (index, child_origin) = Self::child_index(&origin, half, local);
+        let previous = children[index].set(child_origin, half, local, value);
+
+        if let Some(collapsed) = Self::uniform_value(children) {
+            *self = OctreeNode::Leaf(collapsed);
+        }
+
+        previous
+    }
+
+    // Sets every cell of region (in local coordinates) to value, whole subtrees inside of region
+    // are replaced by a single leaf.
+    fn fill(&mut self, origin: Vec3I, size: TransformInt, region: &CuboidI, value: Option<T>) {
+        let bounds = Self::bounds(origin, size);
+
+        if region.contains_cuboid(&bounds) {
+            *self = OctreeNode::Leaf(value);
+            return;
+        }
+        if bounds.intersection(region).is_none() {
+            return;
+        }
+
+        if let OctreeNode::Leaf(current) = self {
+            if *current == value {
+                return;
+            }
+
+            let current = *current;
+            *self =
+                OctreeNode::Branch(Box::new(std::array::from_fn(|_| OctreeNode::Leaf(current))));
+        }
+
+        let OctreeNode::Branch(children) = self else {
+            unreachable!("octree leaf was split into a branch");
+        };
+
+        let half = size / 2;
+        for (index, child) in children.iter_mut().enumerate() {
+            let child_origin = Self::child_origin(&origin, half, index);
+            child.fill(child_origin, half, region, value);
+        }
+
+        if let Some(collapsed) = Self::uniform_value(children) {
+            *self = OctreeNode::Leaf(collapsed);
+        }
+    }
+
+    fn uniform_value(children: &[OctreeNode<T>; 8]) -> Option<Option<T>> {
+        let OctreeNode::Leaf(first) = &children[0] else {
+            return None;
+        };
+
+        for child in children.iter().skip(1) {
+            match child {
+                OctreeNode::Leaf(value) if value == first => continue,
+                _ => return None,
+            }
+        }
+
+        Some(*first)
+    }
+
+    fn collect_leaves(
+        &self,
+        origin: Vec3I,
+        size: TransformInt,
+        offset: &Vec3I,
+        cells: &mut Vec<CuboidI>,
+    ) {
+        match self {
+            OctreeNode::Leaf(None) => {}
+            OctreeNode::Leaf(Some(_)) => cells.push(CuboidI {
+                position: Vec3I {
+                    x: origin.x + offset.x,
+                    y: origin.y + offset.y,
+                    z: origin.z + offset.z,
+                },
+                extents: Vec3I {
+                    x: size,
+                    y: size,
+                    z: size,
+                },
+            }),
+            OctreeNode::Branch(children) => {
+                let half = size / 2;
+                for (index, child) in children.iter().enumerate() {
+                    let child_origin = Self::child_origin(&origin, half, index);
+                    child.collect_leaves(child_origin, half, offset, cells);
+                }
+            }
+        }
+    }
+
+    // Counts the occupied cells of region (in local coordinates), uniform leaves add their whole
+    // intersection with region at once.
+    fn count_region(&self, origin: Vec3I, size: TransformInt, region: &CuboidI) -> usize {
+        let Some(intersection) = Self::bounds(origin, size).intersection(region) else {
+            return 0;
+        };
+
+        match self {
+            OctreeNode::Leaf(None) => 0,
+            OctreeNode::Leaf(Some(_)) => intersection.volume(),
+            OctreeNode::Branch(children) => {
+                let half = size / 2;
+                children
+                    .iter()
+                    .enumerate()
+                    .map(|(index, child)| {
+                        let child_origin = Self::child_origin(&origin, half, index);
+                        child.count_region(child_origin, half, region)
+                    })
+                    .sum()
+            }
+        }
+    }
+
+    // Cells covered by the node with the given local origin and size.
+    fn bounds(origin: Vec3I, size: TransformInt) -> CuboidI {
+        CuboidI {
+            position: origin,
+            extents: Vec3I {
+                x: size,
+                y: size,
+                z: size,
+            },
+        }
+    }
+
+    fn node_count(&self) -> usize {
+        match self {
+            OctreeNode::Leaf(_) => 1,
+            OctreeNode::Branch(children) => {
+                1 + children
+                    .iter()
+                    .map(|child| child.node_count())
+                    .sum::<usize>()
+            }
+        }
+    }
+}
+
+// Depth first iterator over the occupied cells of the tree inside of region (in local
+// coordinates), collapsed leaves are expanded into every cell of their intersection with region.
+// Subtrees outside of region are skipped without visiting their cells.
+struct OctreeIter<'a, T: Copy + Clone + Default + PartialEq + Send + Sync> {
+    stack: Vec<(&'a OctreeNode<T>, Vec3I, TransformInt)>,
+    leaf: Option<(&'a T, CuboidI, usize)>,
+    region: CuboidI,
+    offset: Vec3I,
+}
+
+impl<'a, T: Copy + Clone + Default + PartialEq + Send + Sync> Iterator for OctreeIter<'a, T> {
+    type Item = (Vec3I, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((value, cuboid, index)) = &mut self.leaf {
+                if *index < cuboid.volume() {
+                    let position = cuboid.cell_at(*index);
+                    *index += 1;
+                    return Some((position, *value));
+                }
+                self.leaf = None;
+            }
+
+            let (node, origin, size) = self.stack.pop()?;
+            let Some(intersection) =
+                OctreeNode::<T>::bounds(origin, size).intersection(&self.region)
+            else {
+                continue;
+            };
+
+            match node {
+                OctreeNode::Leaf(None) => {}
+                OctreeNode::Leaf(Some(value)) => {
+                    let cuboid = CuboidI {
+                        position: local::local_to_world(&self.offset, &intersection.position),
+                        extents: intersection.extents,
+                    };
+                    self.leaf = Some((value, cuboid, 0));
+                }
+                OctreeNode::Branch(children) => {
+                    let half = size / 2;
+                    for (index, child) in children.iter().enumerate().rev() {
+                        let child_origin = OctreeNode::<T>::child_origin(&origin, half, index);
+                        self.stack.push((child, child_origin, half));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct OctreeVoxelGrid<T: Copy + Clone + Default + PartialEq + Send + Sync> {
+    width: usize,
+    height: usize,
+    depth: usize,
+    size: TransformInt,
+    root: OctreeNode<T>,
+    position: Vec3I,
+    changes: Option<ChangeLog>,
+}
+
+impl<T: Copy + Clone + Default + PartialEq + Send + Sync> OctreeVoxelGrid<T> {
+    const ORIGIN: Vec3I = Vec3I { x: 0, y: 0, z: 0 };
+
+    pub fn new(position: &Vec3I, extents: &Vec3I) -> Self {
+        let width = extents.x.max(0) as usize;
+        let height = extents.y.max(0) as usize;
+        let depth = extents.z.max(0) as usize;
+
+        let size = width.max(height).max(depth).max(1).next_power_of_two() as TransformInt;
+
+        Self {
+            width,
+            height,
+            depth,
+            size,
+            root: OctreeNode::Leaf(None),
+            position: *position,
+            changes: None,
+        }
+    }
+
+    pub fn bounds_as_cuboid(&self) -> CuboidI {
+        CuboidI {
+            position: self.position,
+            extents: Vec3I {
+                x: self.width as TransformInt,
+                y: self.height as TransformInt,
+                z: self.depth as TransformInt,
+            },
+        }
+    }
+
+    // There is no iter_mut, a collapsed leaf is shared by every cell it covers so handing out one
+    // mutable reference per cell is not possible. Use update_cell or upsert_cell instead.
+
+    // Number of nodes currently allocated in the tree, including branch nodes.
+    pub fn node_count(&self) -> usize {
+        self.root.node_count()
+    }
+
+    // None when world is too far from the grid for the local coordinates to fit TransformInt.
+    pub fn world_to_local(&self, world: &Vec3I) -> Option<Vec3I> {
+        local::world_to_local(&self.position, world)
+    }
+
+    // Converts world coordinates into coordinates local to the tree, returns None when the
+    // coordinates are outside of the grid bounds.
+    fn local_position(&self, x: TransformInt, y: TransformInt, z: TransformInt) -> Option<Vec3I> {
+        local::local_in_bounds(
+            &self.position,
+            &Vec3I { x, y, z },
+            self.width,
+            self.height,
+            self.depth,
+        )
+    }
+
+    // Moves region from world coordinates into coordinates local to the tree.
+    fn local_region(&self, region: &CuboidI) -> Option<CuboidI> {
+        Some(CuboidI {
+            position: self.world_to_local(&region.position)?,
+            extents: region.extents,
+        })
+    }
+
+    // Returns every occupied leaf, leaves covering a collapsed region have extents larger than 1.
+    fn as_cuboid_cells(&self) -> Vec<CuboidI> {
+        let mut cells = Vec::new();
+        self.root
+            .collect_leaves(Self::ORIGIN, self.size, &self.position, &mut cells);
+        cells
+    }
+}
+
+impl<T: Copy + Clone + Default + PartialEq + Send + Sync> VoxelGrid<T> for OctreeVoxelGrid<T> {
+    fn contains_cell(&self, x: TransformInt, y: TransformInt, z: TransformInt) -> bool {
+        self.local_position(x, y, z).is_some()
+    }
+
+    fn create_cell(
+        &mut self,
+        x: TransformInt,
+        y: TransformInt,
+        z: TransformInt,
+        value: &T,
+    ) -> bool {
+        let Some(local) = self.local_position(x, y, z) else {
+            return false;
+        };
+        if self.root.get(Self::ORIGIN, self.size, &local).is_none() {
+            self.root.set(Self::ORIGIN, self.size, &local, Some(*value));
+            if let Some(changes) = &mut self.changes {
+                changes.record_cell(GridChangeKind::Created, x, y, z);
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    fn read_cell(&self, x: TransformInt, y: TransformInt, z: TransformInt) -> Option<&T> {
+        let local = self.local_position(x, y, z)?;
+        self.root.get(Self::ORIGIN, self.size, &local)
+    }
+
+    fn update_cell(
+        &mut self,
+        x: TransformInt,
+        y: TransformInt,
+        z: TransformInt,
+        value: &T,
+    ) -> bool {
+        let Some(local) = self.local_position(x, y, z) else {
+            return false;
+        };
+        if self.root.get(Self::ORIGIN, self.size, &local).is_some() {
+            self.root.set(Self::ORIGIN, self.size, &local, Some(*value));
+            if let Some(changes) = &mut self.changes {
+                changes.record_cell(GridChangeKind::Updated, x, y, z);
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    fn upsert_cell(&mut self, x: TransformInt, y: TransformInt, z: TransformInt, value: &T) {
+        let Some(local) = self.local_position(x, y, z) else {
+            return;
+        };
+        let previous = self.root.set(Self::ORIGIN, self.size, &local, Some(*value));
+
+        if let Some(changes) = &mut self.changes {
+            let kind = if previous.is_none() {
+                GridChangeKind::Created
+            } else {
+                GridChangeKind::Updated
+            };
+            changes.record_cell(kind, x, y, z);
+        }
+    }
+
+    fn delete_cell_pop(&mut self, x: TransformInt, y: TransformInt, z: TransformInt) -> Option<T> {
+        let local = self.local_position(x, y, z)?;
+        let value = self.root.set(Self::ORIGIN, self.size, &local, None)?;
+
+        if let Some(changes) = &mut self.changes {
+            changes.record_cell(GridChangeKind::Deleted, x, y, z);
+        }
+
+        Some(value)
+    }
+
+    fn change_log(&mut self) -> &mut Option<ChangeLog> {
+        &mut self.changes
+    }
+
+    fn iter<'a>(&'a self) -> impl Iterator<Item = (Vec3I, &'a T)>
+    where
+        T: 'a,
+    {
+        OctreeIter {
+            stack: vec![(&self.root, Self::ORIGIN, self.size)],
+            leaf: None,
+            region: OctreeNode::<T>::bounds(Self::ORIGIN, self.size),
+            offset: self.position,
+        }
+    }
+
+    fn iter_region<'a>(&'a self, region: &CuboidI) -> impl Iterator<Item = (Vec3I, &'a T)>
+    where
+        T: 'a,
+    {
+        let region = self
+            .bounds_as_cuboid()
+            .intersection(region)
+            .and_then(|region| self.local_region(&region));
+
+        // An empty stack iterates nothing when region is outside of the grid.
+        OctreeIter {
+            stack: region
+                .map(|_| (&self.root, Self::ORIGIN, self.size))
+                .into_iter()
+                .collect(),
+            leaf: None,
+            region: region.unwrap_or(OctreeNode::<T>::bounds(Self::ORIGIN, 0)),
+            offset: self.position,
+        }
+    }
+
+    fn fill_region(&mut self, region: &CuboidI, value: &T) -> bool {
+        if !self.contains_region(region) {
+            return false;
+        }
+        let Some(local) = self.local_region(region) else {
+            return false;
+        };
+
+        with_region_change(self, GridChangeKind::Updated, region, |grid| {
+            grid.root
+                .fill(Self::ORIGIN, grid.size, &local, Some(*value));
+        });
+
+        true
+    }
+
+    fn clear_region(&mut self, region: &CuboidI) -> usize {
+        let Some(region) = self.bounds_as_cuboid().intersection(region) else {
+            return 0;
+        };
+        let Some(local) = self.local_region(&region) else {
+            return 0;
+        };
+        let cleared = self.root.count_region(Self::ORIGIN, self.size, &local);
+
+        with_region_change(self, GridChangeKind::Deleted, &region, |grid| {
+            grid.root.fill(Self::ORIGIN, grid.size, &local, None);
+        });
+
+        cleared
+    }
+}
+
+impl<T: Copy + Clone + Default + PartialEq + Send + Sync + Traversable> PathfindingGrid
+    for OctreeVoxelGrid<T>
+{
+    fn traversal_cost_i(&self, position: &Vec3I) -> Option<f32> {
+        cell_traversal_cost(self.read_cell(position.x, position.y, position.z))
+    }
+
+    fn position_i(&self) -> Vec3I {
+        self.position
+    }
+
+    fn position_f(&self) -> Vec3F {
+        let pos_i = self.position;
+
+        Vec3F {
+            x: pos_i.x as TransformFloat,
+            y: pos_i.y as TransformFloat,
+            z: pos_i.z as TransformFloat,
+        }
+    }
+
+    fn as_cuboid_cells(&self) -> Vec<CuboidI> {
+        self.as_cuboid_cells()
+    }
+
+    fn compute_path_i(&self, query: &PathQuery) -> Result<PathResult, PathError> {
+        query.engine.compute_path_i(self, query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collapses_uniform_regions() {
+        let position = Vec3I {
+            x: -2,
+            y: -2,
+            z: -2,
+        };
+        let extents = Vec3I { x: 4, y: 4, z: 4 };
+        let mut grid = OctreeVoxelGrid::<u8>::new(&position, &extents);
+
+        for x in -2..2 {
+            for y in -2..2 {
+                for z in -2..2 {
+                    grid.upsert_cell(x, y, z, &7);
+                }
+            }
+        }
+
+        assert_eq!(grid.node_count(), 1);
+        let cells = grid.as_cuboid_cells();
+        assert_eq!(cells.len(), 1);
+        assert_eq!(cells[0].position, position);
+        assert_eq!(cells[0].extents, extents);
+
+        assert_eq!(grid.delete_cell_pop(-2, -2, -2), Some(7));
+        assert!(grid.read_cell(-2, -2, -2).is_none());
+        assert_eq!(grid.read_cell_cloned(1, 1, 1), Some(7));
+        assert_eq!(grid.as_cuboid_cells().len(), 7 + 7);
+
+        assert!(grid.create_cell(-2, -2, -2, &7));
+        assert_eq!(grid.node_count(), 1);
+    }
+
+    #[test]
+    fn rejects_out_of_bounds_coordinates() {
+        let position = Vec3I { x: 0, y: 0, z: 0 };
+        let extents = Vec3I { x: 3, y: 5, z: 3 };
+        let mut grid = OctreeVoxelGrid::<u8>::new(&position, &extents);
+
+        assert!(grid.create_cell(2, 4, 2, &1));
+        assert!(!grid.create_cell(3, 0, 0, &1));
+        assert!(!grid.create_cell(0, 5, 0, &1));
+        assert!(!grid.create_cell(0, 0, -1, &1));
+        assert!(!grid.update_cell(0, 0, 0, &1));
+    }
+
+    #[test]
+    fn region_edits_replace_whole_subtrees() {
+        let position = Vec3I { x: 0, y: 0, z: 0 };
+        let extents = Vec3I {
+            x: 64,
+            y: 64,
+            z: 64,
+        };
+        let mut grid = OctreeVoxelGrid::<u8>::new(&position, &extents);
+
+        assert!(grid.fill_region(&grid.bounds_as_cuboid(), &3));
+        assert_eq!(grid.node_count(), 1);
+
+        let hole = CuboidI {
+            position: Vec3I { x: 0, y: 0, z: 0 },
+            extents: Vec3I {
+                x: 32,
+                y: 32,
+                z: 32,
+            },
+        };
+        assert_eq!(grid.clear_region(&hole), 32 * 32 * 32);
+        assert_eq!(grid.node_count(), 9);
+        assert!(grid.read_cell(31, 31, 31).is_none());
+        assert_eq!(grid.read_cell_cloned(32, 0, 0), Some(3));
+    }
+
+    #[test]
+    fn region_queries_clip_collapsed_leaves() {
+        let position = Vec3I {
+            x: -8,
+            y: -8,
+            z: -8,
+        };
+        let extents = Vec3I {
+            x: 24,
+            y: 24,
+            z: 24,
+        };
+        let mut grid = OctreeVoxelGrid::<u8>::new(&position, &extents);
+        assert!(grid.fill_region(&grid.bounds_as_cuboid(), &1));
+        grid.delete_cell(0, 0, 0);
+
+        // Unaligned with the leaves of the tree and reaching past the grid bounds.
+        let region = CuboidI {
+            position: Vec3I {
+                x: -3,
+                y: -3,
+                z: -3,
+            },
+            extents: Vec3I { x: 100, y: 5, z: 6 },
+        };
+        let expected: Vec<Vec3I> = grid
+            .bounds_as_cuboid()
+            .intersection(&region)
+            .unwrap()
+            .iter_cells()
+            .filter(|&cell| cell != Vec3I { x: 0, y: 0, z: 0 })
+            .collect();
+
+        let mut cells: Vec<Vec3I> = grid.iter_region(&region).map(|(cell, _)| cell).collect();
+        assert_eq!(cells.len(), expected.len());
+        cells.retain(|cell| !expected.contains(cell));
+        assert!(cells.is_empty());
+
+        assert_eq!(grid.clear_region(&region), expected.len());
+        assert_eq!(grid.iter_region(&region).count(), 0);
+        assert_eq!(grid.clear_region(&region), 0);
+        assert_eq!(grid.read_cell_cloned(-4, -3, -3), Some(1));
+    }
+}
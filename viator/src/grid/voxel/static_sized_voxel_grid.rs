@@ -1,8 +1,14 @@
-use crate::path::jps::jps_i;
-use std::collections::HashSet;
-
 use crate::{
-    grid::grid::PathfindingGrid,
+    grid::{
+        change::ChangeLog,
+        grid::{PathfindingGrid, VoxelGrid},
+        voxel::{dense::DenseCells, local},
+    },
+    path::{
+        cost::{cell_traversal_cost, Traversable},
+        query::PathQuery,
+        result::{PathError, PathResult},
+    },
     primitive::{
         alias::{TransformFloat, TransformInt},
         cuboid::CuboidI,
@@ -10,289 +16,203 @@ use crate::{
     },
 };
 
-// Example statically sized grid implementation that supports any size up to the max value of
-// TransformInt. Implemented with a macro to allow for easily defining different capacities.
-
-// ToDo: Add support for negative coordinates, currently grid will crash if operations are
-//       performed on negative coordinates
-
-macro_rules! create_voxel_grid_type {
-    ($type_name:ident, $max_capacity:expr) => {
-        #[derive(Debug)]
-        pub struct $type_name<T: Copy + Clone + Default + Send + Sync> {
-            width: usize,
-            height: usize,
-            depth: usize,
-            grid: [T; $max_capacity],
-            occupied: HashSet<usize>,
-            position: Vec3I,
-        }
+// Example statically sized grid implementation that supports any size up to CAPACITY cells. The
+// backing storage is allocated once on the heap when the grid is constructed and never grows or
+// reallocates afterwards, use one of the aliases below or pick a custom capacity.
+
+// Cell coordinates passed to the grid are world coordinates, the grid covers the cells from
+// position (inclusive) to position + extents (exclusive) on every axis. Coordinates may be
+// negative, operations on cells outside of the grid bounds are rejected.
+
+pub type StaticSizedVoxelGridSmall<T> = StaticSizedVoxelGrid<T, 10_000>;
+pub type StaticSizedVoxelGridMedium<T> = StaticSizedVoxelGrid<T, 50_000>;
+pub type StaticSizedVoxelGridLarge<T> = StaticSizedVoxelGrid<T, 100_000>;
+pub type StaticSizedVoxelGridXL<T> = StaticSizedVoxelGrid<T, 1_000_000>;
+pub type StaticSizedVoxelGridXXL<T> = StaticSizedVoxelGrid<T, 100_000_000>;
 
-        impl<T: Copy + Clone + Default + Send + Sync> $type_name<T> {
-            pub fn new(position: &Vec3I, extents: &Vec3I) -> Self {
-                let width = extents.x as usize;
-                let height = extents.y as usize;
-                let depth = extents.z as usize;
-
-                Self {
-                    width,
-                    height,
-                    depth,
-                    grid: [T::default(); $max_capacity],
-                    occupied: HashSet::new(),
-                    position: position.clone(),
-                }
-            }
-
-            pub fn bounds_as_cuboid(&self) -> CuboidI {
-                CuboidI {
-                    position: self.position.clone(),
-                    extents: Vec3I {
-                        x: self.width as TransformInt,
-                        y: self.height as TransformInt,
-                        z: self.depth as TransformInt,
-                    },
-                }
-            }
-
-            pub fn create_cell(
-                &mut self,
-                x: TransformInt,
-                y: TransformInt,
-                z: TransformInt,
-                value: &T,
-            ) -> bool {
-                let index = self.index_1d(x, y, z);
-                if index >= $max_capacity {
-                    return false;
-                }
-                if !self.occupied.contains(&index) {
-                    self.grid[index] = value.clone();
-                    self.occupied.insert(index);
-                    true
-                } else {
-                    false
-                }
-            }
-
-            pub fn read_cell(
-                &self,
-                x: TransformInt,
-                y: TransformInt,
-                z: TransformInt,
-            ) -> Option<&T> {
-                let index = self.index_1d(x, y, z);
-                if index >= $max_capacity {
-                    return None;
-                }
-                if self.occupied.contains(&index) {
-                    Some(&self.grid[index])
-                } else {
-                    None
-                }
-            }
-
-            pub fn read_cell_cloned(
-                &self,
-                x: TransformInt,
-                y: TransformInt,
-                z: TransformInt,
-            ) -> Option<T> {
-                self.read_cell(x, y, z).cloned()
-            }
-
-            pub fn update_cell(
-                &mut self,
-                x: TransformInt,
-                y: TransformInt,
-                z: TransformInt,
-                value: &T,
-            ) -> bool {
-                let index = self.index_1d(x, y, z);
-                if index >= $max_capacity {
-                    return false;
-                }
-                if self.occupied.contains(&index) {
-                    self.grid[index] = value.clone();
-                    true
-                } else {
-                    false
-                }
-            }
-
-            pub fn upsert_cell(
-                &mut self,
-                x: TransformInt,
-                y: TransformInt,
-                z: TransformInt,
-                value: &T,
-            ) {
-                let index = self.index_1d(x, y, z);
-                if index >= $max_capacity {
-                    return;
-                }
-                self.grid[index] = value.clone();
-                self.occupied.insert(index);
-            }
-
-            pub fn delete_cell(&mut self, x: TransformInt, y: TransformInt, z: TransformInt) {
-                let index = self.index_1d(x, y, z);
-                if index >= $max_capacity {
-                    return;
-                }
-                if self.occupied.remove(&index) {
-                    self.grid[index] = T::default();
-                }
-            }
-
-            pub fn delete_cell_check(
-                &mut self,
-                x: TransformInt,
-                y: TransformInt,
-                z: TransformInt,
-            ) -> bool {
-                let index = self.index_1d(x, y, z);
-                if index >= $max_capacity {
-                    return false;
-                }
-                if self.occupied.remove(&index) {
-                    self.grid[index] = T::default();
-                    true
-                } else {
-                    false
-                }
-            }
-
-            pub fn delete_cell_pop(
-                &mut self,
-                x: TransformInt,
-                y: TransformInt,
-                z: TransformInt,
-            ) -> Option<T> {
-                let index = self.index_1d(x, y, z);
-                if index >= $max_capacity {
-                    return None;
-                }
-                if self.occupied.remove(&index) {
-                    Some(std::mem::replace(&mut self.grid[index], T::default()))
-                } else {
-                    None
-                }
-            }
-
-            fn index_1d(&self, x: TransformInt, y: TransformInt, z: TransformInt) -> usize {
-                let self_pos = self.position;
-
-                #[cfg(feature = "y_up")]
-                {
-                    // X, Z, Y
-                    ((x + self_pos.x)
-                        + ((z + self_pos.z) * self.width as TransformInt)
-                        + ((y + self_pos.y)
-                            * self.width as TransformInt
-                            * self.depth as TransformInt)) as usize
-                }
-
-                #[cfg(feature = "z_up")]
-                {
-                    // X, Y, Z
-                    ((x + self_pos.x)
-                        + ((y + self_pos.y) * self.width as TransformInt)
-                        + ((z + self_pos.z)
-                            * self.width as TransformInt
-                            * self.depth as TransformInt)) as usize
-                }
-            }
-
-            fn index_3d(&self, index: usize) -> Vec3I {
-                let index = index as TransformInt;
-                let width = self.width as TransformInt;
-                let depth = self.depth as TransformInt;
-
-                let x: TransformInt;
-                let y: TransformInt;
-                let z: TransformInt;
-
-                #[cfg(feature = "y_up")]
-                {
-                    x = index % width;
-                    z = (index / width) % depth;
-                    y = index / (width * depth);
-                }
-
-                #[cfg(feature = "z_up")]
-                {
-                    x = index % width;
-                    y = (index / width) % depth;
-                    z = index / (width * depth);
-                }
-
-                Vec3I { x, y, z }
-            }
-
-            fn as_cuboid_cells(&self) -> Vec<CuboidI> {
-                let mut cells = Vec::with_capacity(self.grid.len());
-
-                for (idx, _) in self.grid.iter().enumerate() {
-                    if !self.occupied.contains(&idx) {
-                        continue;
-                    }
-
-                    let cell = CuboidI {
-                        position: self.index_3d(idx),
-                        extents: Vec3I { x: 1, y: 1, z: 1 },
-                    };
-                    cells.push(cell);
-                }
-
-                cells
-            }
-
-            fn is_valid_path_node_i(&self, position: &Vec3I) -> bool {
-                let index = self.index_1d(position.x, position.y, position.z);
-                self.occupied.contains(&index)
-            }
+#[derive(Debug)]
+pub struct StaticSizedVoxelGrid<T: Copy + Clone + Default + Send + Sync, const CAPACITY: usize> {
+    // Every one of the CAPACITY slots is allocated on construction.
+    cells: DenseCells<Box<[T]>>,
+}
+
+impl<T: Copy + Clone + Default + Send + Sync, const CAPACITY: usize>
+    StaticSizedVoxelGrid<T, CAPACITY>
+{
+    // Returns None when the requested extents hold more cells than CAPACITY.
+    pub fn new(position: &Vec3I, extents: &Vec3I) -> Option<Self> {
+        let bounds = CuboidI {
+            position: *position,
+            extents: Vec3I {
+                x: extents.x.max(0),
+                y: extents.y.max(0),
+                z: extents.z.max(0),
+            },
+        };
+
+        let size = (bounds.extents.x as usize)
+            .checked_mul(bounds.extents.y as usize)?
+            .checked_mul(bounds.extents.z as usize)?;
+        if size > CAPACITY {
+            return None;
         }
 
-        impl<T: Copy + Clone + Default + Send + Sync> PathfindingGrid for $type_name<T> {
-            fn is_valid_path_node_i(&self, position: &Vec3I) -> bool {
-                self.is_valid_path_node_i(position)
-            }
-
-            fn position_i(&self) -> Vec3I {
-                self.position
-            }
-
-            fn position_f(&self) -> crate::primitive::vector::Vec3F {
-                let pos_i = self.position.clone();
-
-                Vec3F {
-                    x: pos_i.x as TransformFloat,
-                    y: pos_i.y as TransformFloat,
-                    z: pos_i.z as TransformFloat,
-                }
-            }
-
-            fn as_cuboid_cells(&self) -> Vec<CuboidI> {
-                self.as_cuboid_cells()
-            }
-
-            fn compute_path_i(
-                &self,
-                start: &Vec3I,
-                goal: &Vec3I,
-                pathfinding_engine: &crate::path::path::PathfindingType,
-            ) -> Option<Vec<Vec3I>> {
-                match pathfinding_engine {
-                    crate::path::path::PathfindingType::Jps => jps_i(start, goal, self),
-                }
-            }
+        Some(Self {
+            cells: DenseCells::new(&bounds, vec![T::default(); CAPACITY].into_boxed_slice()),
+        })
+    }
+
+    pub const fn capacity(&self) -> usize {
+        CAPACITY
+    }
+
+    pub fn bounds_as_cuboid(&self) -> CuboidI {
+        *self.cells.bounds()
+    }
+
+    // None when world is too far from the grid for the local coordinates to fit TransformInt.
+    pub fn world_to_local(&self, world: &Vec3I) -> Option<Vec3I> {
+        local::world_to_local(&self.cells.bounds().position, world)
+    }
+
+    pub fn local_to_world(&self, local: &Vec3I) -> Vec3I {
+        local::local_to_world(&self.cells.bounds().position, local)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Vec3I, &mut T)> + '_ {
+        self.cells.iter_mut()
+    }
+}
+
+impl<T: Copy + Clone + Default + Send + Sync, const CAPACITY: usize> VoxelGrid<T>
+    for StaticSizedVoxelGrid<T, CAPACITY>
+{
+    fn contains_cell(&self, x: TransformInt, y: TransformInt, z: TransformInt) -> bool {
+        self.cells.index(x, y, z).is_some()
+    }
+
+    fn create_cell(
+        &mut self,
+        x: TransformInt,
+        y: TransformInt,
+        z: TransformInt,
+        value: &T,
+    ) -> bool {
+        self.cells.create(x, y, z, value)
+    }
+
+    fn read_cell(&self, x: TransformInt, y: TransformInt, z: TransformInt) -> Option<&T> {
+        self.cells.read(x, y, z)
+    }
+
+    fn update_cell(
+        &mut self,
+        x: TransformInt,
+        y: TransformInt,
+        z: TransformInt,
+        value: &T,
+    ) -> bool {
+        self.cells.update(x, y, z, value)
+    }
+
+    fn upsert_cell(&mut self, x: TransformInt, y: TransformInt, z: TransformInt, value: &T) {
+        self.cells.upsert(x, y, z, value);
+    }
+
+    fn delete_cell_pop(&mut self, x: TransformInt, y: TransformInt, z: TransformInt) -> Option<T> {
+        self.cells.delete(x, y, z)
+    }
+
+    fn change_log(&mut self) -> &mut Option<ChangeLog> {
+        self.cells.changes_mut()
+    }
+
+    fn iter<'a>(&'a self) -> impl Iterator<Item = (Vec3I, &'a T)>
+    where
+        T: 'a,
+    {
+        self.cells.iter()
+    }
+
+    fn iter_region<'a>(&'a self, region: &CuboidI) -> impl Iterator<Item = (Vec3I, &'a T)>
+    where
+        T: 'a,
+    {
+        self.cells.iter_region(region)
+    }
+}
+
+impl<T: Copy + Clone + Default + Send + Sync + Traversable, const CAPACITY: usize> PathfindingGrid
+    for StaticSizedVoxelGrid<T, CAPACITY>
+{
+    fn traversal_cost_i(&self, position: &Vec3I) -> Option<f32> {
+        cell_traversal_cost(self.read_cell(position.x, position.y, position.z))
+    }
+
+    fn position_i(&self) -> Vec3I {
+        self.cells.bounds().position
+    }
+
+    fn position_f(&self) -> crate::primitive::vector::Vec3F {
+        let pos_i = self.cells.bounds().position;
+
+        Vec3F {
+            x: pos_i.x as TransformFloat,
+            y: pos_i.y as TransformFloat,
+            z: pos_i.z as TransformFloat,
         }
-    };
+    }
+
+    fn as_cuboid_cells(&self) -> Vec<CuboidI> {
+        self.cells.as_cuboid_cells()
+    }
+
+    fn compute_path_i(&self, query: &PathQuery) -> Result<PathResult, PathError> {
+        query.engine.compute_path_i(self, query)
+    }
 }
 
-create_voxel_grid_type!(StaticSizedVoxelGridSmall, 10_000);
-create_voxel_grid_type!(StaticSizedVoxelGridMedium, 50_000);
-create_voxel_grid_type!(StaticSizedVoxelGridLarge, 100_000);
-create_voxel_grid_type!(StaticSizedVoxelGridXL, 1_000_000);
-create_voxel_grid_type!(StaticSizedVoxelGridXXL, 100_000_000);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitive::cell::Cell;
+
+    #[test]
+    fn rejects_extents_exceeding_capacity() {
+        let position = Vec3I { x: 0, y: 0, z: 0 };
+
+        let extents = Vec3I {
+            x: 10,
+            y: 10,
+            z: 10,
+        };
+        assert!(StaticSizedVoxelGrid::<Cell, 1_000>::new(&position, &extents).is_some());
+
+        let extents = Vec3I {
+            x: 10,
+            y: 10,
+            z: 11,
+        };
+        assert!(StaticSizedVoxelGrid::<Cell, 1_000>::new(&position, &extents).is_none());
+    }
+
+    #[test]
+    fn constructs_large_grids_on_the_heap() {
+        let position = Vec3I {
+            x: -50,
+            y: -50,
+            z: -50,
+        };
+        let extents = Vec3I {
+            x: 100,
+            y: 100,
+            z: 100,
+        };
+        let mut grid = StaticSizedVoxelGridXL::<Cell>::new(&position, &extents).unwrap();
+
+        assert!(grid.create_cell(-50, -50, -50, &Cell));
+        assert!(grid.create_cell(49, 49, 49, &Cell));
+        assert!(!grid.create_cell(50, 0, 0, &Cell));
+        assert_eq!(grid.as_cuboid_cells().len(), 2);
+    }
+}
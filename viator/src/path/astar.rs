@@ -0,0 +1,141 @@
+use crate::{
+    grid::grid::PathfindingGrid,
+    path::{
+        incremental::IncrementalSearch,
+        path::PathfindingEngine,
+        query::PathQuery,
+        result::{PathError, PathResult},
+    },
+};
+
+// Plain A* over voxel grids, every neighbor allowed by the movement rules is searched. Slower
+// than jump point search on large open areas but makes no assumptions about the layout of the
+// traversal costs, which makes it the baseline the other engines are measured against.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AStar;
+
+impl PathfindingEngine for AStar {
+    fn compute_path_i(
+        &self,
+        grid: &dyn PathfindingGrid,
+        query: &PathQuery,
+    ) -> Result<PathResult, PathError> {
+        astar_i(grid, query)
+    }
+}
+
+// Runs an incremental search to completion in one go.
+pub fn astar_i(grid: &dyn PathfindingGrid, query: &PathQuery) -> Result<PathResult, PathError> {
+    IncrementalSearch::new(query).run(grid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        grid::{grid::VoxelGrid, voxel::dynamic_sized_voxel_grid::DynamicSizedVoxelGrid},
+        path::neighborhood::{CornerCutting, MovementRules, Neighborhood},
+        primitive::cell::Cell,
+        primitive::vector::Vec3I,
+    };
+
+    // A U shaped floor around the missing cell at (1, 1, 0), every diagonal step between the two
+    // arms passes alongside it.
+    fn corner_grid() -> DynamicSizedVoxelGrid<Cell> {
+        let mut grid =
+            DynamicSizedVoxelGrid::new(&Vec3I { x: 0, y: 0, z: 0 }, &Vec3I { x: 3, y: 3, z: 1 });
+        for (x, y) in [(0, 0), (1, 0), (2, 0), (2, 1), (2, 2), (0, 1)] {
+            grid.create_cell(x, y, 0, &Cell);
+        }
+        grid
+    }
+
+    #[test]
+    fn corner_cutting_rule_decides_diagonal_steps() {
+        let grid = corner_grid();
+        let start = Vec3I { x: 0, y: 1, z: 0 };
+        let goal = Vec3I { x: 2, y: 1, z: 0 };
+
+        let allow = MovementRules::new(Neighborhood::Eighteen, CornerCutting::Allow);
+        let result = astar_i(&grid, &PathQuery::new(&start, &goal).rules(allow)).unwrap();
+        assert_eq!(result.path.len(), 3);
+        assert!((result.cost - 2.0 * std::f32::consts::SQRT_2).abs() < 1e-5);
+
+        let forbid = MovementRules::new(Neighborhood::Eighteen, CornerCutting::Forbid);
+        let result = astar_i(&grid, &PathQuery::new(&start, &goal).rules(forbid)).unwrap();
+        assert_eq!(result.cost, 4.0);
+        assert_eq!(
+            result.path,
+            vec![
+                start,
+                Vec3I { x: 0, y: 0, z: 0 },
+                Vec3I { x: 1, y: 0, z: 0 },
+                Vec3I { x: 2, y: 0, z: 0 },
+                goal,
+            ]
+        );
+    }
+
+    #[test]
+    fn neighborhood_limits_the_steps() {
+        let mut grid =
+            DynamicSizedVoxelGrid::new(&Vec3I { x: 0, y: 0, z: 0 }, &Vec3I { x: 2, y: 2, z: 2 });
+        grid.create_cell(0, 0, 0, &Cell);
+        grid.create_cell(1, 1, 0, &Cell);
+        grid.create_cell(1, 1, 1, &Cell);
+
+        let start = Vec3I { x: 0, y: 0, z: 0 };
+        let edge = Vec3I { x: 1, y: 1, z: 0 };
+        let corner = Vec3I { x: 1, y: 1, z: 1 };
+
+        let six = MovementRules::new(Neighborhood::Six, CornerCutting::Allow);
+        let error = astar_i(&grid, &PathQuery::new(&start, &edge).rules(six)).unwrap_err();
+        assert!(matches!(error, PathError::GoalUnreachable { .. }));
+        assert_eq!(error.stats().unwrap().nodes_expanded, 1);
+
+        let eighteen = MovementRules::new(Neighborhood::Eighteen, CornerCutting::Allow);
+        assert_eq!(
+            astar_i(&grid, &PathQuery::new(&start, &edge).rules(eighteen))
+                .unwrap()
+                .path
+                .len(),
+            2
+        );
+        assert_eq!(
+            astar_i(&grid, &PathQuery::new(&start, &corner).rules(eighteen))
+                .unwrap()
+                .path
+                .len(),
+            3
+        );
+
+        let twenty_six = MovementRules::new(Neighborhood::TwentySix, CornerCutting::Allow);
+        assert_eq!(
+            astar_i(&grid, &PathQuery::new(&start, &corner).rules(twenty_six))
+                .unwrap()
+                .path
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn rejects_blocked_start_and_goal() {
+        let grid = corner_grid();
+        let rules = MovementRules::default();
+
+        let open = Vec3I { x: 0, y: 0, z: 0 };
+        let blocked = Vec3I { x: 1, y: 1, z: 0 };
+        let outside = Vec3I { x: 5, y: 0, z: 0 };
+
+        assert_eq!(
+            astar_i(&grid, &PathQuery::new(&blocked, &open).rules(rules)),
+            Err(PathError::StartBlocked)
+        );
+        assert_eq!(
+            astar_i(&grid, &PathQuery::new(&open, &outside).rules(rules)),
+            Err(PathError::GoalBlocked)
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    any::Any,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use crate::{
+    grid::grid::PathfindingGrid,
+    path::{
+        astar::AStar,
+        incremental::IncrementalSearch,
+        path::{PathfindingEngine, PathfindingType},
+        query::PathQuery,
+        result::{PathError, PathResult},
+    },
+    primitive::vector::Vec3I,
+};
+
+// Resolves many (start, goal) pairs against a single grid in parallel, the results are returned
+// in the order of the pairs. Everything but start and goal is taken from template, including its
+// engine. Every thread runs one search after the other, A* searches reuse their open and closed
+// lists between pairs.
+
+pub fn compute_paths_i(
+    grid: &dyn PathfindingGrid,
+    pairs: &[(Vec3I, Vec3I)],
+    template: &PathQuery,
+) -> Vec<Result<PathResult, PathError>> {
+    let thread_count = thread::available_parallelism().map_or(1, |count| count.get());
+    compute_paths_i_with_threads(grid, pairs, template, thread_count)
+}
+
+pub fn compute_paths_i_with_threads(
+    grid: &dyn PathfindingGrid,
+    pairs: &[(Vec3I, Vec3I)],
+    template: &PathQuery,
+    thread_count: usize,
+) -> Vec<Result<PathResult, PathError>> {
+    let thread_count = thread_count.clamp(1, pairs.len().max(1));
+    // Threads take the next unresolved pair until none are left, which keeps them busy even when
+    // a few searches take much longer than the rest.
+    let next_pair = AtomicUsize::new(0);
+
+    let resolved: Vec<Vec<(usize, Result<PathResult, PathError>)>> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..thread_count)
+            .map(|_| scope.spawn(|| batch_worker(grid, pairs, template, &next_pair)))
+            .collect();
+
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("batch path worker panicked"))
+            .collect()
+    });
+
+    let mut results: Vec<Option<Result<PathResult, PathError>>> = vec![None; pairs.len()];
+    for (index, result) in resolved.into_iter().flatten() {
+        results[index] = Some(result);
+    }
+
+    results
+        .into_iter()
+        .map(|result| result.expect("every pair is resolved by a worker"))
+        .collect()
+}
+
+fn batch_worker(
+    grid: &dyn PathfindingGrid,
+    pairs: &[(Vec3I, Vec3I)],
+    template: &PathQuery,
+    next_pair: &AtomicUsize,
+) -> Vec<(usize, Result<PathResult, PathError>)> {
+    let mut query = template.clone();
+    let mut search = runs_astar(query.engine.as_ref()).then(|| IncrementalSearch::new(&query));
+    let mut resolved = Vec::new();
+
+    loop {
+        let index = next_pair.fetch_add(1, Ordering::Relaxed);
+        let Some((start, goal)) = pairs.get(index) else {
+            return resolved;
+        };
+
+        query.start = *start;
+        query.goal = *goal;
+        let result = match &mut search {
+            Some(search) => {
+                search.reset(&query);
+                search.run(grid)
+            }
+            None => query.engine.compute_path_i(grid, &query),
+        };
+        resolved.push((index, result));
+    }
+}
+
+// Only the shipped A* engines are swapped for a reused search, custom engines wrapping A* are
+// run as they are.
+fn runs_astar(engine: &dyn PathfindingEngine) -> bool {
+    let engine = engine as &dyn Any;
+    engine.is::<AStar>() || engine.downcast_ref() == Some(&PathfindingType::AStar)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        grid::grid::VoxelGrid,
+        path::{astar::astar_i, fixture::open_grid, query::Heuristic, theta::theta_star_i},
+    };
+
+    #[test]
+    fn returns_results_in_pair_order() {
+        let mut grid = open_grid(&Vec3I { x: 8, y: 8, z: 8 });
+        let region = grid.bounds_as_cuboid();
+        grid.delete_cell(7, 7, 7);
+
+        let cells: Vec<Vec3I> = region.iter_cells().collect();
+        let pairs: Vec<_> = (0..64)
+            .map(|index| {
+                (
+                    cells[index * 7 % cells.len()],
+                    cells[index * 31 % cells.len()],
+                )
+            })
+            .collect();
+        let template = PathQuery::new(&pairs[0].0, &pairs[0].1);
+
+        for thread_count in [1, 4] {
+            let results = compute_paths_i_with_threads(&grid, &pairs, &template, thread_count);
+            assert_eq!(results.len(), pairs.len());
+
+            for ((start, goal), result) in pairs.iter().zip(results) {
+                let expected = astar_i(&grid, &PathQuery::new(start, goal));
+                match (result, expected) {
+                    (Ok(result), Ok(expected)) => {
+                        assert_eq!(result.path.first(), Some(start));
+                        assert_eq!(result.path.last(), Some(goal));
+                        assert!((result.cost - expected.cost).abs() < 1e-4);
+                    }
+                    (result, expected) => assert_eq!(result, expected),
+                }
+            }
+        }
+
+        assert!(compute_paths_i(&grid, &[], &template).is_empty());
+    }
+
+    #[test]
+    fn runs_the_engine_of_the_template() {
+        let grid = open_grid(&Vec3I { x: 8, y: 8, z: 1 });
+
+        let pairs = [
+            (Vec3I { x: 0, y: 0, z: 0 }, Vec3I { x: 7, y: 3, z: 0 }),
+            (Vec3I { x: 6, y: 7, z: 0 }, Vec3I { x: 1, y: 0, z: 0 }),
+        ];
+        let template = PathQuery::new(&pairs[0].0, &pairs[0].1)
+            .engine(PathfindingType::ThetaStar)
+            .heuristic(Heuristic::Euclidean);
+
+        for ((start, goal), result) in pairs.iter().zip(compute_paths_i(&grid, &pairs, &template)) {
+            let mut query = template.clone();
+            query.start = *start;
+            query.goal = *goal;
+            assert_eq!(
+                result.unwrap().path,
+                theta_star_i(&grid, &query).unwrap().path
+            );
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use std::{
+    collections::{BinaryHeap, HashMap},
+    time::Instant,
+};
+
+use crate::{
+    grid::grid::PathfindingGrid,
+    path::{
+        neighborhood::{step, step_cost},
+        node::{reconstruct_path, OpenNode},
+        path::PathfindingEngine,
+        query::PathQuery,
+        result::{check_path_endpoints, PathError, PathResult, PathStats},
+    },
+    primitive::vector::Vec3I,
+};
+
+// Bidirectional A* over voxel grids, one search runs forwards from the start and another one
+// backwards from the goal. Every expansion goes to the search with the smaller open list, when
+// the goal is walled in the backward search runs out of cells after searching the enclosed volume
+// only. With Heuristic::Zero both searches are plain Dijkstra searches.
+//
+// The searches stop once the cheapest path through a cell reached by both can not be beaten by
+// either open list, which keeps the path optimal for admissible heuristics.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Bidirectional;
+
+impl PathfindingEngine for Bidirectional {
+    fn compute_path_i(
+        &self,
+        grid: &dyn PathfindingGrid,
+        query: &PathQuery,
+    ) -> Result<PathResult, PathError> {
+        bidirectional_i(grid, query)
+    }
+}
+
+struct BidirectionalSearch {
+    // Cell the search heads for, the goal for the forward search and the start for the backward
+    // one.
+    target: Vec3I,
+    reverse: bool,
+    open_list: BinaryHeap<OpenNode>,
+    came_from: HashMap<Vec3I, Option<Vec3I>>,
+    cost_so_far: HashMap<Vec3I, f32>,
+}
+
+impl BidirectionalSearch {
+    fn new(query: &PathQuery, origin: &Vec3I, target: &Vec3I, reverse: bool) -> Self {
+        let mut search = Self {
+            target: *target,
+            reverse,
+            open_list: BinaryHeap::new(),
+            came_from: HashMap::new(),
+            cost_so_far: HashMap::new(),
+        };
+
+        search.came_from.insert(*origin, None);
+        search.cost_so_far.insert(*origin, 0.0);
+        search.open_list.push(OpenNode {
+            position: *origin,
+            cost: search.estimate(query, origin),
+            cost_so_far: 0.0,
+        });
+        search
+    }
+
+    fn estimate(&self, query: &PathQuery, position: &Vec3I) -> f32 {
+        query
+            .heuristic
+            .estimate(query.rules.neighborhood, position, &self.target)
+            * query.heuristic_weight
+    }
+
+    // Cheapest up to date node of the open list, dropping the stale ones on top of it.
+    fn peek(&mut self) -> Option<OpenNode> {
+        while let Some(&top) = self.open_list.peek() {
+            if top.cost_so_far <= self.cost_so_far[&top.position] {
+                return Some(top);
+            }
+            self.open_list.pop();
+        }
+
+        None
+    }
+
+    // Cost of the step between two neighbors in the direction of the search.
+    fn step_cost(
+        &self,
+        grid: &dyn PathfindingGrid,
+        query: &PathQuery,
+        position: &Vec3I,
+        direction: &Vec3I,
+    ) -> Option<f32> {
+        if !self.reverse {
+            return step_cost(grid, &query.rules, position, direction);
+        }
+
+        let previous = step(position, direction);
+        if !grid.is_valid_path_node_i(&previous) {
+            return None;
+        }
+
+        let back = Vec3I {
+            x: -direction.x,
+            y: -direction.y,
+            z: -direction.z,
+        };
+        step_cost(grid, &query.rules, &previous, &back)
+    }
+
+    // Cells from the origin of the search to position, both included.
+    fn path_to(&self, position: &Vec3I) -> Vec<Vec3I> {
+        reconstruct_path(*position, |current| self.came_from[&current])
+    }
+}
+
+pub fn bidirectional_i(
+    grid: &dyn PathfindingGrid,
+    query: &PathQuery,
+) -> Result<PathResult, PathError> {
+    let started = Instant::now();
+    let mut stats = PathStats::default();
+    let (start, goal) = (&query.start, &query.goal);
+    check_path_endpoints(grid, start, goal)?;
+
+    let mut forward = BidirectionalSearch::new(query, start, goal, false);
+    let mut backward = BidirectionalSearch::new(query, goal, start, true);
+    let mut over_max_path_cost = false;
+    // Cell reached by both searches on the cheapest path found so far, and its cost.
+    let mut best = (start == goal).then_some((*start, 0.0));
+    stats.record_open_list(2);
+
+    while let (Some(forward_top), Some(backward_top)) = (forward.peek(), backward.peek()) {
+        if best.is_some_and(|(_, cost)| forward_top.cost >= cost || backward_top.cost >= cost) {
+            break;
+        }
+
+        if query.is_expansion_budget_exhausted(&stats) {
+            stats.elapsed = started.elapsed();
+            return Err(PathError::BudgetExhausted { stats });
+        }
+        stats.nodes_expanded += 1;
+
+        let (search, other) = if forward.open_list.len() <= backward.open_list.len() {
+            (&mut forward, &backward)
+        } else {
+            (&mut backward, &forward)
+        };
+        let current = search.open_list.pop().unwrap();
+
+        for direction in query.rules.neighborhood.directions() {
+            let Some(step_cost) = search.step_cost(grid, query, &current.position, direction)
+            else {
+                continue;
+            };
+
+            let new_position = step(&current.position, direction);
+            let new_cost = current.cost_so_far + step_cost;
+
+            if query.exceeds_max_path_cost(new_cost) {
+                over_max_path_cost = true;
+                continue;
+            }
+
+            if search
+                .cost_so_far
+                .get(&new_position)
+                .is_some_and(|&cost| cost <= new_cost)
+            {
+                continue;
+            }
+
+            search.cost_so_far.insert(new_position, new_cost);
+            search
+                .came_from
+                .insert(new_position, Some(current.position));
+            search.open_list.push(OpenNode {
+                position: new_position,
+                cost: new_cost + search.estimate(query, &new_position),
+                cost_so_far: new_cost,
+            });
+
+            if let Some(&other_cost) = other.cost_so_far.get(&new_position) {
+                let cost = new_cost + other_cost;
+                if best.is_none_or(|(_, best)| cost < best) && !query.exceeds_max_path_cost(cost) {
+                    best = Some((new_position, cost));
+                }
+            }
+        }
+        stats.record_open_list(forward.open_list.len() + backward.open_list.len());
+    }
+
+    stats.elapsed = started.elapsed();
+    let Some((meeting, cost)) = best else {
+        return if over_max_path_cost {
+            Err(PathError::BudgetExhausted { stats })
+        } else {
+            Err(PathError::GoalUnreachable { stats })
+        };
+    };
+
+    let mut path = forward.path_to(&meeting);
+    let mut rest = backward.path_to(&meeting);
+    rest.pop();
+    path.extend(rest.into_iter().rev());
+
+    Ok(PathResult { path, cost, stats })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        grid::grid::VoxelGrid,
+        path::{astar::astar_i, fixture::open_grid, query::Heuristic},
+        primitive::cuboid::CuboidI,
+    };
+
+    #[test]
+    fn matches_astar_costs() {
+        let mut grid = open_grid(&Vec3I { x: 8, y: 8, z: 8 });
+        // Pillars scattered over the grid.
+        for position in grid.bounds_as_cuboid().iter_cells() {
+            if (position.x * 7 + position.y * 3 + position.z * 5) % 4 == 0 {
+                grid.delete_cell(position.x, position.y, position.z);
+            }
+        }
+
+        let cells: Vec<Vec3I> = grid.iter().map(|(position, _)| position).collect();
+        for heuristic in [Heuristic::Octile, Heuristic::Zero] {
+            for (index, start) in cells.iter().enumerate().step_by(37) {
+                let goal = cells[(index * 13 + 5) % cells.len()];
+                let query = PathQuery::new(start, &goal).heuristic(heuristic);
+
+                match (bidirectional_i(&grid, &query), astar_i(&grid, &query)) {
+                    (Ok(result), Ok(expected)) => {
+                        assert!((result.cost - expected.cost).abs() < 1e-4);
+                        assert_eq!(result.path[0], *start);
+                        assert_eq!(result.path[result.path.len() - 1], goal);
+                        for pair in result.path.windows(2) {
+                            let direction = Vec3I {
+                                x: pair[1].x - pair[0].x,
+                                y: pair[1].y - pair[0].y,
+                                z: pair[1].z - pair[0].z,
+                            };
+                            assert!(step_cost(&grid, &query.rules, &pair[0], &direction).is_some());
+                        }
+                    }
+                    (Err(error), Err(expected)) => {
+                        assert!(matches!(error, PathError::GoalUnreachable { .. }));
+                        assert!(matches!(expected, PathError::GoalUnreachable { .. }));
+                    }
+                    (result, expected) => panic!("{result:?} != {expected:?}"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn walled_in_goals_fail_fast() {
+        let mut grid = open_grid(&Vec3I {
+            x: 16,
+            y: 16,
+            z: 16,
+        });
+        let shell = CuboidI {
+            position: Vec3I {
+                x: 10,
+                y: 10,
+                z: 10,
+            },
+            extents: Vec3I { x: 3, y: 3, z: 3 },
+        };
+        let goal = Vec3I {
+            x: 11,
+            y: 11,
+            z: 11,
+        };
+        for position in shell.iter_cells().filter(|&position| position != goal) {
+            grid.delete_cell(position.x, position.y, position.z);
+        }
+
+        let query = PathQuery::new(&Vec3I { x: 0, y: 0, z: 0 }, &goal);
+        let error = bidirectional_i(&grid, &query).unwrap_err();
+        let expected = astar_i(&grid, &query).unwrap_err();
+
+        assert!(matches!(error, PathError::GoalUnreachable { .. }));
+        assert!(error.stats().unwrap().nodes_expanded < 10);
+        assert!(expected.stats().unwrap().nodes_expanded > 1000);
+    }
+}
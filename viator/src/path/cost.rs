@@ -0,0 +1,56 @@
+// Implemented by the cell types stored in voxel grids to drive pathfinding. Occupied cells are the
+// nodes agents may stand on, the stored value decides whether the node can be entered at all and
+// how expensive entering it is (mud, water, hazards).
+pub trait Traversable {
+    fn is_passable(&self) -> bool {
+        true
+    }
+
+    // Multiplier applied to the length of every move entering the cell. Costs below 1.0 make the
+    // distance heuristics overestimate, pathfinders may return sub-optimal paths in that case.
+    // Must be finite and greater than 0.0, the searches rely on path costs growing with every
+    // step. Other costs trip a debug assertion and make the cell impassable in release builds.
+    fn traversal_cost(&self) -> f32 {
+        1.0
+    }
+}
+
+// Cost of entering a grid cell, None for empty and impassable cells.
+pub fn cell_traversal_cost<T: Traversable>(cell: Option<&T>) -> Option<f32> {
+    let cost = cell
+        .filter(|cell| cell.is_passable())
+        .map(|cell| cell.traversal_cost())?;
+
+    debug_assert!(
+        cost.is_finite() && cost > 0.0,
+        "traversal cost must be finite and positive, got {cost}"
+    );
+    (cost.is_finite() && cost > 0.0).then_some(cost)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Weighted(f32);
+
+    impl Traversable for Weighted {
+        fn traversal_cost(&self) -> f32 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn passes_positive_costs_through() {
+        assert_eq!(cell_traversal_cost(Some(&Weighted(2.5))), Some(2.5));
+        assert_eq!(cell_traversal_cost::<Weighted>(None), None);
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, should_panic(expected = "traversal cost"))]
+    fn rejects_costs_that_are_not_positive() {
+        for cost in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert_eq!(cell_traversal_cost(Some(&Weighted(cost))), None);
+        }
+    }
+}
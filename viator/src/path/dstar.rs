@@ -0,0 +1,488 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap, HashSet},
+    time::Instant,
+};
+
+use crate::{
+    grid::{change::GridChange, grid::PathfindingGrid},
+    path::{
+        neighborhood::{step, step_cost},
+        query::PathQuery,
+        result::{check_path_endpoints, PathError, PathResult, PathStats},
+    },
+    primitive::{cuboid::CuboidI, vector::Vec3I},
+};
+
+// D* Lite planner, keeps its search state between calls so a path invalidated by edits to the
+// grid is repaired instead of searched from scratch. The search runs backwards from the goal, the
+// start may move along the path between calls without discarding anything.
+//
+// Enable change tracking on the grid and hand the changes taken from it to apply_changes before
+// the next call to compute_path_i, planners sharing a grid all need the same changes. The engine
+// of the query is ignored, the heuristic has to be consistent (no weights above 1.0) for repaired
+// paths to stay optimal.
+
+#[derive(Copy, Clone)]
+struct DStarLiteNode {
+    pub position: Vec3I,
+    pub key: DStarLiteKey,
+}
+
+// Compared lexicographically.
+type DStarLiteKey = (f32, f32);
+
+fn dstar_lite_compare(a: &DStarLiteKey, b: &DStarLiteKey) -> Ordering {
+    a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1))
+}
+
+impl PartialEq for DStarLiteNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for DStarLiteNode {}
+
+impl Ord for DStarLiteNode {
+    // Smallest key first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        dstar_lite_compare(&other.key, &self.key)
+    }
+}
+
+impl PartialOrd for DStarLiteNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub struct DStarLite {
+    query: PathQuery,
+    // Start as of the last applied changes, the heuristic offset grows by the distance moved
+    // since then.
+    last_start: Vec3I,
+    key_offset: f32,
+    // Cost from a cell to the goal, g is the settled cost and rhs the one looking ahead one step.
+    g: HashMap<Vec3I, f32>,
+    rhs: HashMap<Vec3I, f32>,
+    open_list: BinaryHeap<DStarLiteNode>,
+    // Current key of every cell in the open list, heap entries with another key are stale.
+    open_keys: HashMap<Vec3I, DStarLiteKey>,
+    over_max_path_cost: bool,
+}
+
+impl DStarLite {
+    pub fn new(query: &PathQuery) -> Self {
+        let mut planner = Self {
+            query: query.clone(),
+            last_start: query.start,
+            key_offset: 0.0,
+            g: HashMap::new(),
+            rhs: HashMap::new(),
+            open_list: BinaryHeap::new(),
+            open_keys: HashMap::new(),
+            over_max_path_cost: false,
+        };
+
+        let goal = query.goal;
+        planner.rhs.insert(goal, 0.0);
+        let key = planner.key(&goal);
+        planner.push(goal, key);
+        planner
+    }
+
+    pub fn query(&self) -> &PathQuery {
+        &self.query
+    }
+
+    pub fn start(&self) -> Vec3I {
+        self.query.start
+    }
+
+    // Moves the start, typically to the next cell of the path once the agent reached it.
+    pub fn set_start(&mut self, start: &Vec3I) {
+        self.query.start = *start;
+    }
+
+    // Marks every cell whose outgoing steps may have changed with the edits, the path is repaired
+    // by the next call to compute_path_i.
+    pub fn apply_changes(&mut self, grid: &dyn PathfindingGrid, changes: &[GridChange]) {
+        if changes.is_empty() {
+            return;
+        }
+
+        self.key_offset += self.heuristic(&self.last_start, &self.query.start);
+        self.last_start = self.query.start;
+
+        // Edits change the steps into the edited cells, out of them and, with corner cutting
+        // forbidden, past them, every one of these starts in the 3x3x3 block around an edit. Only
+        // cells the search reached need a new lookahead, every other cell only has neighbors at an
+        // infinite cost and keeps its infinite one. Large regions are matched against the reached
+        // cells instead of walking their volume.
+        let mut dirty = HashSet::new();
+        for change in changes {
+            let region = change.region;
+            let grown = CuboidI {
+                position: Vec3I {
+                    x: region.position.x - 1,
+                    y: region.position.y - 1,
+                    z: region.position.z - 1,
+                },
+                extents: Vec3I {
+                    x: region.extents.x + 2,
+                    y: region.extents.y + 2,
+                    z: region.extents.z + 2,
+                },
+            };
+
+            // Every reached cell has a lookahead, settled ones included.
+            if grown.volume() <= self.rhs.len() {
+                dirty.extend(
+                    grown
+                        .iter_cells()
+                        .filter(|position| self.rhs.contains_key(position)),
+                );
+            } else {
+                dirty.extend(
+                    self.rhs
+                        .keys()
+                        .filter(|position| grown.contains(position))
+                        .copied(),
+                );
+            }
+        }
+
+        for position in dirty {
+            self.update_cell(grid, &position);
+        }
+    }
+
+    // Repairs the search as far as needed for the current start and returns the path from it.
+    // The statistics only cover the work done by this call.
+    pub fn compute_path_i(&mut self, grid: &dyn PathfindingGrid) -> Result<PathResult, PathError> {
+        let started = Instant::now();
+        let mut stats = PathStats::default();
+        let (start, goal) = (self.query.start, self.query.goal);
+        check_path_endpoints(grid, &start, &goal)?;
+
+        stats.record_open_list(self.open_keys.len());
+        let searched = self.search(grid, &mut stats);
+        stats.elapsed = started.elapsed();
+        if !searched {
+            return Err(PathError::BudgetExhausted { stats });
+        }
+
+        let cost = self.g_of(&start);
+        if cost == f32::INFINITY {
+            return if self.over_max_path_cost {
+                Err(PathError::BudgetExhausted { stats })
+            } else {
+                Err(PathError::GoalUnreachable { stats })
+            };
+        }
+        if self.query.exceeds_max_path_cost(cost) {
+            return Err(PathError::BudgetExhausted { stats });
+        }
+
+        let path = self.follow_path(grid);
+        stats.elapsed = started.elapsed();
+        match path {
+            Some(path) => Ok(PathResult { path, cost, stats }),
+            None => Err(PathError::GoalUnreachable { stats }),
+        }
+    }
+
+    // Expands cells until the start is consistent, false if the expansion budget ran out first.
+    fn search(&mut self, grid: &dyn PathfindingGrid, stats: &mut PathStats) -> bool {
+        self.over_max_path_cost = false;
+        let start = self.query.start;
+
+        while let Some(top) = self.pop_open() {
+            let start_key = self.key(&start);
+            if dstar_lite_compare(&top.key, &start_key) != Ordering::Less
+                && self.rhs_of(&start) == self.g_of(&start)
+            {
+                self.open_list.push(top);
+                break;
+            }
+
+            // Cells this far from the goal are only on paths costing more than allowed.
+            if self.query.exceeds_max_path_cost(top.key.1) {
+                self.over_max_path_cost = true;
+                self.open_list.push(top);
+                break;
+            }
+
+            if self.query.is_expansion_budget_exhausted(stats) {
+                self.open_list.push(top);
+                return false;
+            }
+            stats.nodes_expanded += 1;
+
+            let position = top.position;
+            let key = self.key(&position);
+            if dstar_lite_compare(&top.key, &key) == Ordering::Less {
+                self.push(position, key);
+            } else if self.g_of(&position) > self.rhs_of(&position) {
+                self.g.insert(position, self.rhs_of(&position));
+                self.open_keys.remove(&position);
+                self.update_predecessors(grid, &position);
+            } else {
+                self.g.insert(position, f32::INFINITY);
+                self.update_cell(grid, &position);
+                self.update_predecessors(grid, &position);
+            }
+            stats.record_open_list(self.open_keys.len());
+        }
+
+        true
+    }
+
+    // Walks from the start to the goal along the cheapest settled steps.
+    fn follow_path(&self, grid: &dyn PathfindingGrid) -> Option<Vec<Vec3I>> {
+        let mut current = self.query.start;
+        let mut path = vec![current];
+
+        while current != self.query.goal {
+            // Every step has to get closer to the goal, stale costs could form a loop otherwise.
+            let (next, _) = self.cheapest_successor(grid, &current)?;
+            if self.g_of(&next) >= self.g_of(&current) {
+                return None;
+            }
+            current = next;
+            path.push(current);
+        }
+
+        Some(path)
+    }
+
+    fn cheapest_successor(
+        &self,
+        grid: &dyn PathfindingGrid,
+        position: &Vec3I,
+    ) -> Option<(Vec3I, f32)> {
+        let mut best: Option<(Vec3I, f32)> = None;
+
+        for direction in self.query.rules.neighborhood.directions() {
+            let Some(cost) = step_cost(grid, &self.query.rules, position, direction) else {
+                continue;
+            };
+
+            let successor = step(position, direction);
+            let cost = cost + self.g_of(&successor);
+            if cost < f32::INFINITY && best.is_none_or(|(_, best)| cost < best) {
+                best = Some((successor, cost));
+            }
+        }
+
+        best
+    }
+
+    fn update_predecessors(&mut self, grid: &dyn PathfindingGrid, position: &Vec3I) {
+        for direction in self.query.rules.neighborhood.directions() {
+            let predecessor = step(position, direction);
+            self.update_cell(grid, &predecessor);
+        }
+    }
+
+    // Recomputes the one step lookahead of a cell and queues it if it became inconsistent.
+    fn update_cell(&mut self, grid: &dyn PathfindingGrid, position: &Vec3I) {
+        if *position != self.query.goal {
+            let rhs = if grid.is_valid_path_node_i(position) {
+                self.cheapest_successor(grid, position)
+                    .map_or(f32::INFINITY, |(_, cost)| cost)
+            } else {
+                f32::INFINITY
+            };
+            self.rhs.insert(*position, rhs);
+        }
+
+        if self.g_of(position) != self.rhs_of(position) {
+            let key = self.key(position);
+            self.push(*position, key);
+        } else {
+            self.open_keys.remove(position);
+        }
+    }
+
+    fn push(&mut self, position: Vec3I, key: DStarLiteKey) {
+        self.open_keys.insert(position, key);
+        self.open_list.push(DStarLiteNode { position, key });
+    }
+
+    // Pops the smallest up to date entry of the open list, dropping the stale ones on top of it.
+    fn pop_open(&mut self) -> Option<DStarLiteNode> {
+        while let Some(top) = self.open_list.pop() {
+            if self.open_keys.get(&top.position) == Some(&top.key) {
+                return Some(top);
+            }
+        }
+
+        None
+    }
+
+    fn key(&self, position: &Vec3I) -> DStarLiteKey {
+        let cost = self.g_of(position).min(self.rhs_of(position));
+        (
+            cost + self.heuristic(&self.query.start, position) + self.key_offset,
+            cost,
+        )
+    }
+
+    fn heuristic(&self, from: &Vec3I, to: &Vec3I) -> f32 {
+        self.query
+            .heuristic
+            .estimate(self.query.rules.neighborhood, from, to)
+            * self.query.heuristic_weight
+    }
+
+    fn g_of(&self, position: &Vec3I) -> f32 {
+        self.g.get(position).copied().unwrap_or(f32::INFINITY)
+    }
+
+    fn rhs_of(&self, position: &Vec3I) -> f32 {
+        self.rhs.get(position).copied().unwrap_or(f32::INFINITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        grid::{
+            change::GridChangeKind, grid::VoxelGrid,
+            voxel::dynamic_sized_voxel_grid::DynamicSizedVoxelGrid,
+        },
+        path::{astar::astar_i, fixture::open_grid},
+        primitive::cell::Cell,
+    };
+
+    fn tracked_grid() -> DynamicSizedVoxelGrid<Cell> {
+        let mut grid = open_grid(&Vec3I { x: 8, y: 8, z: 3 });
+        grid.track_changes(true);
+        grid
+    }
+
+    fn assert_matches_astar(grid: &dyn PathfindingGrid, result: &PathResult, query: &PathQuery) {
+        let expected = astar_i(grid, query).unwrap();
+        assert!((result.cost - expected.cost).abs() < 1e-4);
+        assert_eq!(result.path.first(), Some(&query.start));
+        assert_eq!(result.path.last(), Some(&query.goal));
+    }
+
+    #[test]
+    fn repairs_the_path_after_edits() {
+        let mut grid = tracked_grid();
+        let query = PathQuery::new(&Vec3I { x: 0, y: 0, z: 1 }, &Vec3I { x: 7, y: 0, z: 1 });
+        let mut planner = DStarLite::new(&query);
+
+        let initial = planner.compute_path_i(&grid).unwrap();
+        assert_matches_astar(&grid, &initial, &query);
+
+        // A wall across the straight line, open at the far end of the y axis.
+        for y in 0..7 {
+            for z in 0..3 {
+                grid.delete_cell(4, y, z);
+            }
+        }
+        let changes = grid.take_changes();
+        planner.apply_changes(&grid, &changes);
+
+        let repaired = planner.compute_path_i(&grid).unwrap();
+        assert_matches_astar(&grid, &repaired, &query);
+        assert!(repaired.cost > initial.cost);
+
+        // Reopening part of the wall shortens the path again.
+        grid.upsert_cell(4, 1, 1, &Cell);
+        let changes = grid.take_changes();
+        planner.apply_changes(&grid, &changes);
+
+        let reopened = planner.compute_path_i(&grid).unwrap();
+        assert_matches_astar(&grid, &reopened, &query);
+        assert!(reopened.cost < repaired.cost);
+
+        // Repairing expands fewer cells than planning from scratch.
+        let fresh = DStarLite::new(&query).compute_path_i(&grid).unwrap();
+        assert!(reopened.stats.nodes_expanded < fresh.stats.nodes_expanded);
+    }
+
+    #[test]
+    fn large_edits_only_revisit_reached_cells() {
+        let mut grid = tracked_grid();
+        let query = PathQuery::new(&Vec3I { x: 0, y: 0, z: 1 }, &Vec3I { x: 7, y: 5, z: 1 });
+        let mut planner = DStarLite::new(&query);
+        let initial = planner.compute_path_i(&grid).unwrap();
+
+        // Walking the volume of this region cell by cell would not finish.
+        let huge = GridChange {
+            kind: GridChangeKind::Deleted,
+            region: CuboidI {
+                position: Vec3I {
+                    x: -4096,
+                    y: -4096,
+                    z: -4096,
+                },
+                extents: Vec3I {
+                    x: 8192,
+                    y: 8192,
+                    z: 8192,
+                },
+            },
+        };
+        grid.delete_cell(3, 0, 1);
+        let mut changes = grid.take_changes();
+        changes.push(huge);
+        planner.apply_changes(&grid, &changes);
+
+        let repaired = planner.compute_path_i(&grid).unwrap();
+        assert_matches_astar(&grid, &repaired, &query);
+        assert!(!repaired.path.contains(&Vec3I { x: 3, y: 0, z: 1 }));
+        assert!(repaired.cost >= initial.cost);
+    }
+
+    #[test]
+    fn follows_a_moving_start() {
+        let mut grid = tracked_grid();
+        let goal = Vec3I { x: 7, y: 7, z: 0 };
+        let mut planner = DStarLite::new(&PathQuery::new(&Vec3I { x: 0, y: 0, z: 0 }, &goal));
+        let path = planner.compute_path_i(&grid).unwrap().path;
+
+        planner.set_start(&path[2]);
+        grid.delete_cell(path[4].x, path[4].y, path[4].z);
+        let changes = grid.take_changes();
+        planner.apply_changes(&grid, &changes);
+
+        let result = planner.compute_path_i(&grid).unwrap();
+        assert_matches_astar(&grid, &result, &PathQuery::new(&path[2], &goal));
+        assert!(!result.path.contains(&path[4]));
+    }
+
+    #[test]
+    fn reports_unreachable_and_blocked_goals() {
+        let mut grid = tracked_grid();
+        let goal = Vec3I { x: 7, y: 7, z: 2 };
+        let mut planner = DStarLite::new(&PathQuery::new(&Vec3I { x: 0, y: 0, z: 0 }, &goal));
+        planner.compute_path_i(&grid).unwrap();
+
+        // Walls the goal in.
+        let goal_block = CuboidI {
+            position: Vec3I { x: 6, y: 6, z: 1 },
+            extents: Vec3I { x: 2, y: 2, z: 2 },
+        };
+        for position in goal_block.iter_cells().filter(|&position| position != goal) {
+            grid.delete_cell(position.x, position.y, position.z);
+        }
+        let changes = grid.take_changes();
+        planner.apply_changes(&grid, &changes);
+        assert!(matches!(
+            planner.compute_path_i(&grid),
+            Err(PathError::GoalUnreachable { .. })
+        ));
+
+        grid.delete_cell(7, 7, 2);
+        let changes = grid.take_changes();
+        planner.apply_changes(&grid, &changes);
+        assert_eq!(planner.compute_path_i(&grid), Err(PathError::GoalBlocked));
+    }
+}
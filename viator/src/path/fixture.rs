@@ -0,0 +1,14 @@
+use crate::{
+    grid::{grid::VoxelGrid, voxel::dynamic_sized_voxel_grid::DynamicSizedVoxelGrid},
+    primitive::{cell::Cell, vector::Vec3I},
+};
+
+// Grids shared by the tests of the path modules.
+
+// Grid at the origin with every cell of extents occupied.
+pub fn open_grid(extents: &Vec3I) -> DynamicSizedVoxelGrid<Cell> {
+    let mut grid = DynamicSizedVoxelGrid::new(&Vec3I { x: 0, y: 0, z: 0 }, extents);
+    let region = grid.bounds_as_cuboid();
+    grid.fill_region(&region, &Cell);
+    grid
+}
@@ -0,0 +1,254 @@
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::{
+    grid::grid::PathfindingGrid,
+    path::{
+        neighborhood::{step, step_cost, MovementRules},
+        node::OpenNode,
+    },
+    primitive::vector::Vec3I,
+};
+
+// Flow field towards one or more goals, built once with a Dijkstra search running backwards from
+// every goal at the same time. Every cell that can reach a goal stores the step towards the
+// closest one, agents look their next step up instead of searching a path each. Edits to the grid
+// are not picked up, rebuild the field once they affect it.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlowCell {
+    // Step to take from the cell, None on the goals.
+    pub direction: Option<Vec3I>,
+    // Cost of the cheapest path from the cell to the closest goal.
+    pub distance: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FlowField {
+    cells: HashMap<Vec3I, FlowCell>,
+}
+
+impl FlowField {
+    pub fn new(grid: &dyn PathfindingGrid, goals: &[Vec3I], rules: &MovementRules) -> Self {
+        Self::with_max_distance(grid, goals, rules, None)
+    }
+
+    // Only cells at most max_distance away from a goal are part of the field, which bounds the
+    // work for fields around a local rally point on large grids.
+    pub fn with_max_distance(
+        grid: &dyn PathfindingGrid,
+        goals: &[Vec3I],
+        rules: &MovementRules,
+        max_distance: Option<f32>,
+    ) -> Self {
+        Self {
+            cells: flow_dijkstra_i(grid, goals, rules, max_distance, true),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    // None if position can not reach any goal.
+    pub fn get(&self, position: &Vec3I) -> Option<&FlowCell> {
+        self.cells.get(position)
+    }
+
+    pub fn direction(&self, position: &Vec3I) -> Option<Vec3I> {
+        self.get(position)?.direction
+    }
+
+    pub fn distance(&self, position: &Vec3I) -> Option<f32> {
+        self.get(position).map(|cell| cell.distance)
+    }
+
+    // Cell reached by following the field for a single step from position.
+    pub fn next_step(&self, position: &Vec3I) -> Option<Vec3I> {
+        Some(step(position, &self.direction(position)?))
+    }
+
+    // Every cell from position to the goal the field leads to, both included.
+    pub fn path_from(&self, position: &Vec3I) -> Option<Vec<Vec3I>> {
+        let mut current = *self.get(position)?;
+        let mut path = vec![*position];
+
+        while let Some(direction) = current.direction {
+            let next = step(&path[path.len() - 1], &direction);
+            current = self.cells[&next];
+            path.push(next);
+        }
+
+        Some(path)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Vec3I, &FlowCell)> {
+        self.cells.iter()
+    }
+}
+
+// Dijkstra search from every source at the same time, valid path nodes among the sources start at
+// a cost of 0.0. Forward searches store the cost of reaching a cell from the closest source and
+// the direction of the step into it, reverse searches follow the steps backwards and store the
+// cost of reaching the closest source from a cell and the direction of the step out of it.
+pub(crate) fn flow_dijkstra_i(
+    grid: &dyn PathfindingGrid,
+    sources: &[Vec3I],
+    rules: &MovementRules,
+    max_cost: Option<f32>,
+    reverse: bool,
+) -> HashMap<Vec3I, FlowCell> {
+    let mut cells = HashMap::new();
+    let mut open_list = BinaryHeap::new();
+
+    for source in sources {
+        if grid.is_valid_path_node_i(source) {
+            cells.insert(
+                *source,
+                FlowCell {
+                    direction: None,
+                    distance: 0.0,
+                },
+            );
+            open_list.push(OpenNode {
+                position: *source,
+                cost: 0.0,
+                cost_so_far: 0.0,
+            });
+        }
+    }
+
+    while let Some(current) = open_list.pop() {
+        if current.cost_so_far > cells[&current.position].distance {
+            continue;
+        }
+
+        for direction in rules.neighborhood.directions() {
+            let next = step(&current.position, direction);
+            if !grid.is_valid_path_node_i(&next) {
+                continue;
+            }
+
+            let back = Vec3I {
+                x: -direction.x,
+                y: -direction.y,
+                z: -direction.z,
+            };
+
+            let (cost, direction) = if reverse {
+                (step_cost(grid, rules, &next, &back), back)
+            } else {
+                (
+                    step_cost(grid, rules, &current.position, direction),
+                    *direction,
+                )
+            };
+            let Some(cost) = cost else {
+                continue;
+            };
+
+            let new_cost = current.cost + cost;
+            if max_cost.is_some_and(|max_cost| new_cost > max_cost) {
+                continue;
+            }
+
+            if cells
+                .get(&next)
+                .is_some_and(|cell: &FlowCell| cell.distance <= new_cost)
+            {
+                continue;
+            }
+
+            cells.insert(
+                next,
+                FlowCell {
+                    direction: Some(direction),
+                    distance: new_cost,
+                },
+            );
+            open_list.push(OpenNode {
+                position: next,
+                cost: new_cost,
+                cost_so_far: new_cost,
+            });
+        }
+    }
+
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        grid::{grid::VoxelGrid, voxel::dynamic_sized_voxel_grid::DynamicSizedVoxelGrid},
+        path::{astar::astar_i, fixture::open_grid, query::PathQuery},
+        primitive::cell::Cell,
+    };
+
+    // An 8x8 floor split by a wall at x = 4, open at the top of the y axis.
+    fn walled_grid() -> DynamicSizedVoxelGrid<Cell> {
+        let mut grid = open_grid(&Vec3I { x: 8, y: 8, z: 1 });
+        for y in 0..7 {
+            grid.delete_cell(4, y, 0);
+        }
+        grid
+    }
+
+    #[test]
+    fn directions_lead_to_the_closest_goal() {
+        let grid = walled_grid();
+        let rules = MovementRules::default();
+        let goals = [Vec3I { x: 0, y: 0, z: 0 }, Vec3I { x: 7, y: 0, z: 0 }];
+        let field = FlowField::new(&grid, &goals, &rules);
+
+        // Every cell but the wall.
+        assert_eq!(field.len(), 64 - 7);
+        assert_eq!(field.direction(&goals[0]), None);
+        assert!(field.get(&Vec3I { x: 4, y: 0, z: 0 }).is_none());
+
+        for (position, cell) in field.iter() {
+            let closest = goals
+                .iter()
+                .map(|goal| {
+                    astar_i(&grid, &PathQuery::new(position, goal))
+                        .unwrap()
+                        .cost
+                })
+                .fold(f32::INFINITY, f32::min);
+            assert!((cell.distance - closest).abs() < 1e-4);
+
+            let path = field.path_from(position).unwrap();
+            assert!(goals.contains(&path[path.len() - 1]));
+            assert_eq!(path.len() > 1, cell.direction.is_some());
+        }
+
+        assert_eq!(
+            field.next_step(&Vec3I { x: 6, y: 1, z: 0 }),
+            Some(Vec3I { x: 7, y: 0, z: 0 })
+        );
+    }
+
+    #[test]
+    fn max_distance_bounds_the_field() {
+        let grid = walled_grid();
+        let goals = [Vec3I { x: 0, y: 0, z: 0 }];
+        let field =
+            FlowField::with_max_distance(&grid, &goals, &MovementRules::default(), Some(2.0));
+
+        // Two straight steps along either axis or a single diagonal one.
+        assert_eq!(field.len(), 6);
+        assert!(field.iter().all(|(_, cell)| cell.distance <= 2.0));
+        assert_eq!(field.distance(&Vec3I { x: 3, y: 0, z: 0 }), None);
+
+        let blocked = FlowField::new(
+            &grid,
+            &[Vec3I { x: 4, y: 0, z: 0 }],
+            &MovementRules::default(),
+        );
+        assert!(blocked.is_empty());
+    }
+}
@@ -0,0 +1,808 @@
+use std::{
+    collections::{BinaryHeap, HashMap, HashSet},
+    time::Instant,
+};
+
+use crate::{
+    grid::{change::GridChange, grid::PathfindingGrid},
+    path::{
+        neighborhood::{step, step_cost, step_length, MovementRules, DIRECTIONS},
+        node::{reconstruct_path, OpenNode},
+        path::PathfindingEngine,
+        query::PathQuery,
+        result::{check_path_endpoints, PathError, PathResult, PathStats},
+    },
+    primitive::{alias::TransformInt, cuboid::CuboidI, vector::Vec3I},
+};
+
+// Hierarchical pathfinding (HPA*) over a region of a grid split into cubic clusters. Steps
+// crossing the border between two clusters are grouped into connected runs and one step per run
+// becomes a transition, the cells on either side of it are the entrances of the clusters. The
+// cheapest paths between the entrances of a cluster, staying inside of it, are cached.
+//
+// Searches plan over the entrances first and refine every abstract step with A* restricted to
+// the clusters of its ends afterwards, the returned path contains every cell like the flat
+// engines but is not guaranteed to be the cheapest one. The movement rules of the graph replace
+// the ones of the query, the expanded node count and its budget cover the abstract search and the
+// refinement.
+//
+// Edits only rebuild the clusters they touch, the neighbors of a cluster are only rebuilt when
+// the entrances on their shared border moved.
+
+// A single step from one cluster into a neighboring one.
+#[derive(Debug, Clone, Copy)]
+struct HpaTransition {
+    from: Vec3I,
+    to: Vec3I,
+    cost: f32,
+}
+
+#[derive(Debug, Clone)]
+struct HpaCluster {
+    region: CuboidI,
+    entrances: Vec<Vec3I>,
+    exits: Vec<HpaTransition>,
+    // Cheapest path between two entrances staying inside of the cluster.
+    costs: HashMap<(Vec3I, Vec3I), f32>,
+}
+
+pub struct HierarchicalGraph {
+    bounds: CuboidI,
+    cluster_size: TransformInt,
+    rules: MovementRules,
+    // Keyed by the position of the cluster in cluster units.
+    clusters: HashMap<Vec3I, HpaCluster>,
+    // Transitions in both directions between two neighboring clusters, keyed by hpa_pair.
+    transitions: HashMap<(Vec3I, Vec3I), Vec<HpaTransition>>,
+}
+
+impl HierarchicalGraph {
+    // Builds the graph for every cell of bounds, cells outside of it are never searched.
+    pub fn new(
+        grid: &dyn PathfindingGrid,
+        bounds: &CuboidI,
+        cluster_size: TransformInt,
+        rules: MovementRules,
+    ) -> Self {
+        let mut graph = Self {
+            bounds: *bounds,
+            cluster_size: cluster_size.max(1),
+            rules,
+            clusters: HashMap::new(),
+            transitions: HashMap::new(),
+        };
+
+        for cluster in graph.clusters_in(bounds) {
+            let region = graph.cluster_region(&cluster);
+            graph.clusters.insert(
+                cluster,
+                HpaCluster {
+                    region,
+                    entrances: Vec::new(),
+                    exits: Vec::new(),
+                    costs: HashMap::new(),
+                },
+            );
+        }
+
+        let clusters: Vec<Vec3I> = graph.clusters.keys().copied().collect();
+        for cluster in &clusters {
+            for neighbor in graph.neighbor_clusters(cluster) {
+                if hpa_order(cluster) < hpa_order(&neighbor) {
+                    graph.build_transitions(grid, cluster, &neighbor);
+                }
+            }
+        }
+
+        for cluster in &clusters {
+            graph.build_entrances(cluster);
+            graph.build_costs(grid, cluster);
+        }
+
+        graph
+    }
+
+    pub fn bounds(&self) -> &CuboidI {
+        &self.bounds
+    }
+
+    pub fn cluster_size(&self) -> TransformInt {
+        self.cluster_size
+    }
+
+    pub fn rules(&self) -> &MovementRules {
+        &self.rules
+    }
+
+    pub fn cluster_count(&self) -> usize {
+        self.clusters.len()
+    }
+
+    pub fn entrance_count(&self) -> usize {
+        self.clusters
+            .values()
+            .map(|cluster| cluster.entrances.len())
+            .sum()
+    }
+
+    // Position of the cluster containing position, in cluster units.
+    pub fn cluster_of(&self, position: &Vec3I) -> Vec3I {
+        Vec3I {
+            x: (position.x - self.bounds.position.x).div_euclid(self.cluster_size),
+            y: (position.y - self.bounds.position.y).div_euclid(self.cluster_size),
+            z: (position.z - self.bounds.position.z).div_euclid(self.cluster_size),
+        }
+    }
+
+    // Rebuilds the parts of the graph affected by the changes and returns the number of clusters
+    // whose cached costs were rebuilt.
+    pub fn apply_changes(&mut self, grid: &dyn PathfindingGrid, changes: &[GridChange]) -> usize {
+        let mut edited = HashSet::new();
+        let mut touched = HashSet::new();
+
+        // Steps next to an edited cell may pass alongside it when corner cutting is forbidden,
+        // every cluster within one cell of an edit has its transitions rebuilt.
+        for change in changes {
+            let region = change.region;
+            edited.extend(self.clusters_in(&region));
+            touched.extend(self.clusters_in(&hpa_grow(&region)));
+        }
+
+        let mut pairs = HashSet::new();
+        for cluster in &touched {
+            for neighbor in self.neighbor_clusters(cluster) {
+                pairs.insert(hpa_pair(cluster, &neighbor));
+            }
+        }
+        for (a, b) in &pairs {
+            self.build_transitions(grid, a, b);
+        }
+
+        let mut rebuilt = 0;
+        let mut affected = edited.clone();
+        affected.extend(pairs.iter().flat_map(|&(a, b)| [a, b]));
+        for cluster in &affected {
+            let entrances = self.clusters[cluster].entrances.clone();
+            self.build_entrances(cluster);
+
+            if edited.contains(cluster) || self.clusters[cluster].entrances != entrances {
+                self.build_costs(grid, cluster);
+                rebuilt += 1;
+            }
+        }
+
+        rebuilt
+    }
+
+    fn clusters_in(&self, region: &CuboidI) -> Vec<Vec3I> {
+        let Some(region) = region.intersection(&self.bounds) else {
+            return Vec::new();
+        };
+
+        let min = self.cluster_of(&region.position);
+        let max = self.cluster_of(&Vec3I {
+            x: region.position.x + region.extents.x - 1,
+            y: region.position.y + region.extents.y - 1,
+            z: region.position.z + region.extents.z - 1,
+        });
+
+        CuboidI {
+            position: min,
+            extents: Vec3I {
+                x: max.x - min.x + 1,
+                y: max.y - min.y + 1,
+                z: max.z - min.z + 1,
+            },
+        }
+        .iter_cells()
+        .collect()
+    }
+
+    fn cluster_region(&self, cluster: &Vec3I) -> CuboidI {
+        let size = self.cluster_size;
+        let region = CuboidI {
+            position: Vec3I {
+                x: self.bounds.position.x + cluster.x * size,
+                y: self.bounds.position.y + cluster.y * size,
+                z: self.bounds.position.z + cluster.z * size,
+            },
+            extents: Vec3I {
+                x: size,
+                y: size,
+                z: size,
+            },
+        };
+
+        region.intersection(&self.bounds).unwrap_or(region)
+    }
+
+    fn neighbor_clusters(&self, cluster: &Vec3I) -> Vec<Vec3I> {
+        DIRECTIONS
+            .iter()
+            .map(|direction| step(cluster, direction))
+            .filter(|neighbor| self.clusters.contains_key(neighbor))
+            .collect()
+    }
+
+    // Finds the steps between two neighboring clusters, groups them into runs of touching cells
+    // and keeps the shortest step in the middle of every run.
+    fn build_transitions(&mut self, grid: &dyn PathfindingGrid, a: &Vec3I, b: &Vec3I) {
+        let key = hpa_pair(a, b);
+        let (a_region, b_region) = (self.clusters[a].region, self.clusters[b].region);
+        let rules = &self.rules;
+
+        let mut crossings = HashMap::<Vec3I, Vec<Vec3I>>::new();
+        if let Some(border) = hpa_grow(&b_region).intersection(&a_region) {
+            for cell in border.iter_cells() {
+                if !grid.is_valid_path_node_i(&cell) {
+                    continue;
+                }
+
+                for direction in rules.neighborhood.directions() {
+                    let next = step(&cell, direction);
+                    if b_region.contains(&next) && grid.is_valid_path_node_i(&next) {
+                        crossings.entry(cell).or_default().push(next);
+                    }
+                }
+            }
+        }
+
+        let mut transitions = Vec::new();
+        let mut visited = HashSet::new();
+        let mut cells: Vec<Vec3I> = crossings.keys().copied().collect();
+        cells.sort_by_key(hpa_order);
+
+        for cell in cells {
+            if !visited.insert(cell) {
+                continue;
+            }
+
+            let mut run = vec![cell];
+            let mut index = 0;
+            while index < run.len() {
+                let current = run[index];
+                index += 1;
+                for direction in &DIRECTIONS {
+                    let next = step(&current, direction);
+                    if crossings.contains_key(&next) && visited.insert(next) {
+                        run.push(next);
+                    }
+                }
+            }
+
+            let from = run[run.len() / 2];
+            let to = crossings[&from]
+                .iter()
+                .copied()
+                .min_by(|x, y| hpa_step_length(&from, x).total_cmp(&hpa_step_length(&from, y)))
+                .unwrap_or(from);
+
+            for (from, to) in [(from, to), (to, from)] {
+                let direction = hpa_direction(&from, &to);
+                if let Some(cost) = step_cost(grid, rules, &from, &direction) {
+                    transitions.push(HpaTransition { from, to, cost });
+                }
+            }
+        }
+
+        self.transitions.insert(key, transitions);
+    }
+
+    fn build_entrances(&mut self, cluster: &Vec3I) {
+        let region = self.clusters[cluster].region;
+        let mut entrances = HashSet::new();
+        let mut exits = Vec::new();
+
+        for neighbor in self.neighbor_clusters(cluster) {
+            let Some(transitions) = self.transitions.get(&hpa_pair(cluster, &neighbor)) else {
+                continue;
+            };
+
+            for transition in transitions {
+                if region.contains(&transition.from) {
+                    entrances.insert(transition.from);
+                    exits.push(*transition);
+                } else {
+                    entrances.insert(transition.to);
+                }
+            }
+        }
+
+        let mut entrances: Vec<Vec3I> = entrances.into_iter().collect();
+        entrances.sort_by_key(hpa_order);
+
+        let cluster = self.clusters.get_mut(cluster).unwrap();
+        cluster.entrances = entrances;
+        cluster.exits = exits;
+    }
+
+    fn build_costs(&mut self, grid: &dyn PathfindingGrid, cluster: &Vec3I) {
+        let (region, entrances) = {
+            let cluster = &self.clusters[cluster];
+            (cluster.region, cluster.entrances.clone())
+        };
+
+        let mut costs = HashMap::new();
+        for from in &entrances {
+            let reached = hpa_region_costs(grid, &self.rules, &region, from, false);
+            for to in &entrances {
+                if let Some(&cost) = reached.get(to).filter(|_| to != from) {
+                    costs.insert((*from, *to), cost);
+                }
+            }
+        }
+
+        self.clusters.get_mut(cluster).unwrap().costs = costs;
+    }
+
+    fn search(
+        &self,
+        grid: &dyn PathfindingGrid,
+        query: &PathQuery,
+        stats: &mut PathStats,
+    ) -> Result<Vec<Vec3I>, bool> {
+        let (start, goal) = (query.start, query.goal);
+        let start_region = self.clusters[&self.cluster_of(&start)].region;
+        let goal_cluster = self.cluster_of(&goal);
+        let goal_region = self.clusters[&goal_cluster].region;
+
+        let from_start = hpa_region_costs(grid, &self.rules, &start_region, &start, false);
+        let to_goal = hpa_region_costs(grid, &self.rules, &goal_region, &goal, true);
+
+        let mut over_max_path_cost = false;
+        let mut came_from = HashMap::<Vec3I, Option<Vec3I>>::new();
+        let mut cost_so_far = HashMap::<Vec3I, f32>::new();
+        let mut open_list = BinaryHeap::new();
+
+        came_from.insert(start, None);
+        cost_so_far.insert(start, 0.0);
+        open_list.push(OpenNode {
+            position: start,
+            cost: query.estimate(&start),
+            cost_so_far: 0.0,
+        });
+        stats.record_open_list(open_list.len());
+
+        while let Some(current) = open_list.pop() {
+            if current.cost_so_far > cost_so_far[&current.position] {
+                continue;
+            }
+
+            if current.position == goal {
+                return Ok(reconstruct_path(goal, |current| came_from[&current]));
+            }
+
+            if query.is_expansion_budget_exhausted(stats) {
+                return Err(true);
+            }
+            stats.nodes_expanded += 1;
+
+            let cluster = &self.clusters[&self.cluster_of(&current.position)];
+            let mut successors = Vec::new();
+            if current.position == start {
+                for to in cluster.entrances.iter().chain([&goal]) {
+                    if let Some(&cost) = from_start.get(to) {
+                        successors.push((*to, cost));
+                    }
+                }
+            } else {
+                for to in &cluster.entrances {
+                    if let Some(&cost) = cluster.costs.get(&(current.position, *to)) {
+                        successors.push((*to, cost));
+                    }
+                }
+                if let Some(&cost) = to_goal.get(&current.position) {
+                    successors.push((goal, cost));
+                }
+            }
+            for exit in &cluster.exits {
+                if exit.from == current.position {
+                    successors.push((exit.to, exit.cost));
+                }
+            }
+
+            for (position, cost) in successors {
+                let new_cost = current.cost_so_far + cost;
+                if query.exceeds_max_path_cost(new_cost) {
+                    over_max_path_cost = true;
+                    continue;
+                }
+
+                if cost_so_far
+                    .get(&position)
+                    .is_some_and(|&cost| cost <= new_cost)
+                {
+                    continue;
+                }
+
+                cost_so_far.insert(position, new_cost);
+                came_from.insert(position, Some(current.position));
+                open_list.push(OpenNode {
+                    position,
+                    cost: new_cost + query.estimate(&position),
+                    cost_so_far: new_cost,
+                });
+                stats.record_open_list(open_list.len());
+            }
+        }
+
+        Err(over_max_path_cost)
+    }
+}
+
+impl PathfindingEngine for HierarchicalGraph {
+    fn compute_path_i(
+        &self,
+        grid: &dyn PathfindingGrid,
+        query: &PathQuery,
+    ) -> Result<PathResult, PathError> {
+        let started = Instant::now();
+        let mut stats = PathStats::default();
+        let (start, goal) = (&query.start, &query.goal);
+        check_path_endpoints(grid, start, goal)?;
+
+        if !self.bounds.contains(start) || !self.bounds.contains(goal) {
+            return Err(PathError::GoalUnreachable { stats });
+        }
+
+        // The estimates have to follow the rules the costs were found with.
+        let query = &query.clone().rules(self.rules);
+        let failed = |budget_exhausted: bool, mut stats: PathStats| {
+            stats.elapsed = started.elapsed();
+            if budget_exhausted {
+                PathError::BudgetExhausted { stats }
+            } else {
+                PathError::GoalUnreachable { stats }
+            }
+        };
+
+        let waypoints = self
+            .search(grid, query, &mut stats)
+            .map_err(|budget_exhausted| failed(budget_exhausted, stats))?;
+
+        // Every abstract step was found inside of the clusters of its ends, refining it inside of
+        // the same clusters finds a path at most as expensive as the cached cost.
+        let mut path = vec![*start];
+        let mut cost = 0.0;
+        for pair in waypoints.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            let regions = [
+                self.clusters[&self.cluster_of(&from)].region,
+                self.clusters[&self.cluster_of(&to)].region,
+            ];
+
+            // Unreachable when the grid was edited without applying the changes to the graph.
+            let (segment, segment_cost) = hpa_refine(grid, query, &regions, &from, &to, &mut stats)
+                .map_err(|budget_exhausted| failed(budget_exhausted, stats))?;
+            path.extend_from_slice(&segment[1..]);
+            cost += segment_cost;
+        }
+
+        stats.elapsed = started.elapsed();
+        Ok(PathResult { path, cost, stats })
+    }
+}
+
+// Dijkstra from origin over the cells of region, reverse follows the steps backwards and returns
+// the cost of reaching origin from every cell instead.
+fn hpa_region_costs(
+    grid: &dyn PathfindingGrid,
+    rules: &MovementRules,
+    region: &CuboidI,
+    origin: &Vec3I,
+    reverse: bool,
+) -> HashMap<Vec3I, f32> {
+    let mut costs = HashMap::new();
+    let mut open_list = BinaryHeap::new();
+
+    costs.insert(*origin, 0.0);
+    open_list.push(OpenNode {
+        position: *origin,
+        cost: 0.0,
+        cost_so_far: 0.0,
+    });
+
+    while let Some(current) = open_list.pop() {
+        if current.cost_so_far > costs[&current.position] {
+            continue;
+        }
+
+        for direction in rules.neighborhood.directions() {
+            let next = step(&current.position, direction);
+            if !region.contains(&next) {
+                continue;
+            }
+
+            let cost = if reverse {
+                step_cost(grid, rules, &next, &hpa_direction(&next, &current.position))
+            } else {
+                step_cost(grid, rules, &current.position, direction)
+            };
+            let Some(cost) = cost else {
+                continue;
+            };
+
+            let new_cost = current.cost_so_far + cost;
+            if costs.get(&next).is_some_and(|&cost| cost <= new_cost) {
+                continue;
+            }
+
+            costs.insert(next, new_cost);
+            open_list.push(OpenNode {
+                position: next,
+                cost: new_cost,
+                cost_so_far: new_cost,
+            });
+        }
+    }
+
+    costs
+}
+
+// A* from from to to over the cells of regions only, fails with false if to can not be reached
+// inside of them and with true once the expansion budget of query ran out. The expanded nodes are
+// added to stats.
+fn hpa_refine(
+    grid: &dyn PathfindingGrid,
+    query: &PathQuery,
+    regions: &[CuboidI],
+    from: &Vec3I,
+    to: &Vec3I,
+    stats: &mut PathStats,
+) -> Result<(Vec<Vec3I>, f32), bool> {
+    let rules = &query.rules;
+    let estimate = |position: &Vec3I| query.heuristic.estimate(rules.neighborhood, position, to);
+    let mut came_from = HashMap::<Vec3I, Option<Vec3I>>::new();
+    let mut cost_so_far = HashMap::<Vec3I, f32>::new();
+    let mut open_list = BinaryHeap::new();
+
+    came_from.insert(*from, None);
+    cost_so_far.insert(*from, 0.0);
+    open_list.push(OpenNode {
+        position: *from,
+        cost: estimate(from),
+        cost_so_far: 0.0,
+    });
+
+    while let Some(current) = open_list.pop() {
+        if current.cost_so_far > cost_so_far[&current.position] {
+            continue;
+        }
+
+        if current.position == *to {
+            let path = reconstruct_path(*to, |current| came_from[&current]);
+            return Ok((path, current.cost_so_far));
+        }
+
+        if query.is_expansion_budget_exhausted(stats) {
+            return Err(true);
+        }
+        stats.nodes_expanded += 1;
+
+        for direction in rules.neighborhood.directions() {
+            let next = step(&current.position, direction);
+            if !regions.iter().any(|region| region.contains(&next)) {
+                continue;
+            }
+            let Some(cost) = step_cost(grid, rules, &current.position, direction) else {
+                continue;
+            };
+
+            let new_cost = current.cost_so_far + cost;
+            if cost_so_far.get(&next).is_some_and(|&cost| cost <= new_cost) {
+                continue;
+            }
+
+            cost_so_far.insert(next, new_cost);
+            came_from.insert(next, Some(current.position));
+            open_list.push(OpenNode {
+                position: next,
+                cost: new_cost + estimate(&next),
+                cost_so_far: new_cost,
+            });
+        }
+        stats.record_open_list(open_list.len());
+    }
+
+    Err(false)
+}
+
+fn hpa_direction(from: &Vec3I, to: &Vec3I) -> Vec3I {
+    Vec3I {
+        x: to.x - from.x,
+        y: to.y - from.y,
+        z: to.z - from.z,
+    }
+}
+
+fn hpa_step_length(from: &Vec3I, to: &Vec3I) -> f32 {
+    step_length(&hpa_direction(from, to))
+}
+
+fn hpa_grow(region: &CuboidI) -> CuboidI {
+    CuboidI {
+        position: Vec3I {
+            x: region.position.x - 1,
+            y: region.position.y - 1,
+            z: region.position.z - 1,
+        },
+        extents: Vec3I {
+            x: region.extents.x + 2,
+            y: region.extents.y + 2,
+            z: region.extents.z + 2,
+        },
+    }
+}
+
+fn hpa_order(position: &Vec3I) -> (TransformInt, TransformInt, TransformInt) {
+    (position.x, position.y, position.z)
+}
+
+// Key of the transitions between two clusters, the same for both orders.
+fn hpa_pair(a: &Vec3I, b: &Vec3I) -> (Vec3I, Vec3I) {
+    if hpa_order(a) <= hpa_order(b) {
+        (*a, *b)
+    } else {
+        (*b, *a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        grid::{grid::VoxelGrid, voxel::dynamic_sized_voxel_grid::DynamicSizedVoxelGrid},
+        path::{
+            astar::astar_i,
+            cost::Traversable,
+            fixture::open_grid,
+            neighborhood::{CornerCutting, Neighborhood},
+        },
+        primitive::cell::Cell,
+    };
+
+    // Two walls across the x axis, the first open at the top of the y axis and the second at the
+    // bottom.
+    fn walled_grid() -> DynamicSizedVoxelGrid<Cell> {
+        let mut grid = open_grid(&Vec3I { x: 16, y: 16, z: 2 });
+        for z in 0..2 {
+            for y in 0..14 {
+                grid.delete_cell(5, y, z);
+            }
+            for y in 2..16 {
+                grid.delete_cell(10, y, z);
+            }
+        }
+        grid.track_changes(true);
+        grid.take_changes();
+        grid
+    }
+
+    fn check_path(grid: &dyn PathfindingGrid, result: &PathResult, query: &PathQuery) {
+        assert_eq!(result.path.first(), Some(&query.start));
+        assert_eq!(result.path.last(), Some(&query.goal));
+        for pair in result.path.windows(2) {
+            let direction = hpa_direction(&pair[0], &pair[1]);
+            assert!(query.rules.neighborhood.contains(&direction));
+            assert!(grid.is_valid_path_node_i(&pair[1]));
+        }
+    }
+
+    #[test]
+    fn refined_paths_stay_close_to_flat_search() {
+        let grid = walled_grid();
+        let rules = MovementRules::default();
+        let graph = HierarchicalGraph::new(&grid, &grid.bounds_as_cuboid(), 4, rules);
+        assert_eq!(graph.cluster_count(), 16);
+
+        let query = PathQuery::new(&Vec3I { x: 0, y: 0, z: 0 }, &Vec3I { x: 15, y: 15, z: 1 });
+        let expected = astar_i(&grid, &query).unwrap();
+
+        let result = grid
+            .compute_path_i(&query.clone().shared_engine(Arc::new(graph)))
+            .unwrap();
+        check_path(&grid, &result, &query);
+        assert!(result.cost >= expected.cost - 1e-3);
+        assert!(result.cost <= expected.cost * 1.25);
+    }
+
+    #[test]
+    fn edits_rebuild_only_the_touched_clusters() {
+        let mut grid = walled_grid();
+        let rules = MovementRules::new(Neighborhood::Six, CornerCutting::Allow);
+        let mut graph = HierarchicalGraph::new(&grid, &grid.bounds_as_cuboid(), 4, rules);
+        let query =
+            PathQuery::new(&Vec3I { x: 0, y: 0, z: 0 }, &Vec3I { x: 15, y: 0, z: 0 }).rules(rules);
+
+        // Away from every border of the first cluster.
+        grid.delete_cell(1, 1, 0);
+        let changes = grid.take_changes();
+        assert_eq!(graph.apply_changes(&grid, &changes), 1);
+        check_path(&grid, &graph.compute_path_i(&grid, &query).unwrap(), &query);
+
+        // Closes the gap in the first wall.
+        for z in 0..2 {
+            grid.delete_cell(5, 14, z);
+            grid.delete_cell(5, 15, z);
+        }
+        let changes = grid.take_changes();
+        assert!(graph.apply_changes(&grid, &changes) >= 1);
+        assert!(matches!(
+            graph.compute_path_i(&grid, &query),
+            Err(PathError::GoalUnreachable { .. })
+        ));
+
+        grid.upsert_cell(5, 8, 0, &Cell);
+        let changes = grid.take_changes();
+        graph.apply_changes(&grid, &changes);
+        let result = graph.compute_path_i(&grid, &query).unwrap();
+        check_path(&grid, &result, &query);
+        assert!(result.path.contains(&Vec3I { x: 5, y: 8, z: 0 }));
+    }
+
+    #[test]
+    fn searches_follow_the_rules_and_budget_of_the_graph() {
+        let grid = walled_grid();
+        let rules = MovementRules::default();
+        let graph = HierarchicalGraph::new(&grid, &grid.bounds_as_cuboid(), 4, rules);
+        let query = PathQuery::new(&Vec3I { x: 0, y: 0, z: 0 }, &Vec3I { x: 15, y: 15, z: 1 });
+
+        // Octile distances of face neighbors overestimate paths taking diagonal steps.
+        let expected = graph.compute_path_i(&grid, &query).unwrap();
+        let result = graph
+            .compute_path_i(&grid, &query.clone().neighborhood(Neighborhood::Six))
+            .unwrap();
+        assert_eq!(
+            result,
+            PathResult {
+                stats: result.stats,
+                ..expected.clone()
+            }
+        );
+
+        // The last nodes expanded belong to the refinement.
+        let nodes_expanded = expected.stats.nodes_expanded;
+        let error = graph
+            .compute_path_i(&grid, &query.clone().max_expanded_nodes(nodes_expanded - 1))
+            .unwrap_err();
+        assert!(matches!(error, PathError::BudgetExhausted { .. }));
+        assert_eq!(error.stats().unwrap().nodes_expanded, nodes_expanded - 1);
+        assert!(graph
+            .compute_path_i(&grid, &query.max_expanded_nodes(nodes_expanded))
+            .is_ok());
+    }
+
+    // Costs in the thousands, large enough for rounding to add up over a long path.
+    #[derive(Debug, Default, Clone, Copy)]
+    struct Heavy(f32);
+
+    impl Traversable for Heavy {
+        fn traversal_cost(&self) -> f32 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn refines_paths_over_expensive_cells() {
+        let mut grid = DynamicSizedVoxelGrid::<Heavy>::new(
+            &Vec3I { x: 0, y: 0, z: 0 },
+            &Vec3I { x: 256, y: 2, z: 2 },
+        );
+        for position in grid.bounds_as_cuboid().iter_cells() {
+            let cost = 1000.0 + (position.x % 7 + position.y * 3 + position.z) as f32 * 0.37;
+            grid.upsert_cell(position.x, position.y, position.z, &Heavy(cost));
+        }
+
+        let rules = MovementRules::default();
+        let graph = HierarchicalGraph::new(&grid, &grid.bounds_as_cuboid(), 8, rules);
+        let query = PathQuery::new(&Vec3I { x: 0, y: 0, z: 0 }, &Vec3I { x: 255, y: 1, z: 1 });
+        let expected = astar_i(&grid, &query).unwrap();
+
+        let result = graph.compute_path_i(&grid, &query).unwrap();
+        check_path(&grid, &result, &query);
+        assert!(result.cost >= expected.cost * (1.0 - 1e-5));
+        assert!(result.cost <= expected.cost * 1.25);
+    }
+}
@@ -0,0 +1,330 @@
+use std::{
+    collections::{BinaryHeap, HashMap},
+    time::Instant,
+};
+
+use crate::{
+    grid::grid::PathfindingGrid,
+    path::{
+        neighborhood::{step, step_cost},
+        node::{reconstruct_path, OpenNode},
+        query::PathQuery,
+        result::{check_path_endpoints, PathError, PathResult, PathStats},
+    },
+    primitive::vector::Vec3I,
+};
+
+// Resumable A* search, every call to step expands a bounded number of nodes so long searches can
+// be spread over several frames. The grid is passed to every step instead of being borrowed for
+// the lifetime of the search, edits made to it between steps are picked up by the remaining
+// steps: queued nodes are checked again when they are expanded, the nodes already expanded are
+// kept as they are. The engine of the query is ignored,
+// jump point search can not bound the work done by a single expansion.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchStatus {
+    InProgress,
+    Found(PathResult),
+    Failed(PathError),
+}
+
+// Outcome of a single step, turned into a SearchStatus once the statistics are final.
+enum Expansion {
+    InProgress,
+    Found { path: Vec<Vec3I>, cost: f32 },
+    Blocked(PathError),
+    Unreachable,
+    BudgetExhausted,
+}
+
+pub struct IncrementalSearch {
+    query: PathQuery,
+    open_list: BinaryHeap<OpenNode>,
+    came_from: HashMap<Vec3I, Option<Vec3I>>,
+    cost_so_far: HashMap<Vec3I, f32>,
+    over_max_path_cost: bool,
+    stats: PathStats,
+    // Set once the search finished, returned by every later step.
+    outcome: Option<SearchStatus>,
+}
+
+impl IncrementalSearch {
+    pub fn new(query: &PathQuery) -> Self {
+        let mut search = Self {
+            query: query.clone(),
+            open_list: BinaryHeap::new(),
+            came_from: HashMap::new(),
+            cost_so_far: HashMap::new(),
+            over_max_path_cost: false,
+            stats: PathStats::default(),
+            outcome: None,
+        };
+        search.reset(query);
+        search
+    }
+
+    // Restarts the search for query, keeping the allocations of the previous search.
+    pub fn reset(&mut self, query: &PathQuery) {
+        self.query = query.clone();
+        self.open_list.clear();
+        self.came_from.clear();
+        self.cost_so_far.clear();
+        self.over_max_path_cost = false;
+        self.stats = PathStats::default();
+        self.outcome = None;
+
+        self.open_list.push(OpenNode {
+            position: query.start,
+            cost: query.estimate(&query.start),
+            cost_so_far: 0.0,
+        });
+        self.came_from.insert(query.start, None);
+        self.cost_so_far.insert(query.start, 0.0);
+        self.stats.record_open_list(self.open_list.len());
+    }
+
+    pub fn query(&self) -> &PathQuery {
+        &self.query
+    }
+
+    // Statistics of the search so far, elapsed only counts the time spent inside of step.
+    pub fn stats(&self) -> &PathStats {
+        &self.stats
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.outcome.is_some()
+    }
+
+    // Expands at most max_expansions nodes.
+    pub fn step(&mut self, grid: &dyn PathfindingGrid, max_expansions: usize) -> SearchStatus {
+        if let Some(outcome) = &self.outcome {
+            return outcome.clone();
+        }
+
+        let started = Instant::now();
+        let expansion = self.expand(grid, max_expansions);
+        self.stats.elapsed += started.elapsed();
+
+        let stats = self.stats;
+        let status = match expansion {
+            Expansion::InProgress => return SearchStatus::InProgress,
+            Expansion::Found { path, cost } => {
+                SearchStatus::Found(PathResult { path, cost, stats })
+            }
+            Expansion::Blocked(error) => SearchStatus::Failed(error),
+            Expansion::Unreachable => SearchStatus::Failed(PathError::GoalUnreachable { stats }),
+            Expansion::BudgetExhausted => {
+                SearchStatus::Failed(PathError::BudgetExhausted { stats })
+            }
+        };
+
+        self.outcome = Some(status.clone());
+        status
+    }
+
+    // Steps until the search finishes.
+    pub fn run(&mut self, grid: &dyn PathfindingGrid) -> Result<PathResult, PathError> {
+        loop {
+            match self.step(grid, usize::MAX) {
+                SearchStatus::InProgress => {}
+                SearchStatus::Found(result) => return Ok(result),
+                SearchStatus::Failed(error) => return Err(error),
+            }
+        }
+    }
+
+    fn expand(&mut self, grid: &dyn PathfindingGrid, max_expansions: usize) -> Expansion {
+        let query = &self.query;
+
+        if self.stats.nodes_expanded == 0 {
+            if let Err(error) = check_path_endpoints(grid, &query.start, &query.goal) {
+                return Expansion::Blocked(error);
+            }
+        }
+
+        let mut expansions = 0;
+        while let Some(current) = self.open_list.pop() {
+            if self.cost_so_far.get(&current.position) != Some(&current.cost_so_far) {
+                continue;
+            }
+
+            // The grid may have changed since the node was queued, the step into it is checked
+            // again and its cost read again. Nodes that can no longer be entered are forgotten so
+            // any other neighbor may queue them again.
+            match self.queued_cost(grid, &current.position) {
+                None => {
+                    self.cost_so_far.remove(&current.position);
+                    continue;
+                }
+                Some(cost_so_far) if cost_so_far != current.cost_so_far => {
+                    self.cost_so_far.insert(current.position, cost_so_far);
+                    self.open_list.push(OpenNode {
+                        position: current.position,
+                        cost: cost_so_far + query.estimate(&current.position),
+                        cost_so_far,
+                    });
+                    continue;
+                }
+                Some(_) => {}
+            }
+
+            if current.position == query.goal {
+                return Expansion::Found {
+                    path: reconstruct_path(query.goal, |current| self.came_from[&current]),
+                    cost: current.cost_so_far,
+                };
+            }
+
+            if query.is_expansion_budget_exhausted(&self.stats) {
+                return Expansion::BudgetExhausted;
+            }
+
+            if expansions == max_expansions {
+                self.open_list.push(current);
+                return Expansion::InProgress;
+            }
+            expansions += 1;
+            self.stats.nodes_expanded += 1;
+
+            for direction in query.rules.neighborhood.directions() {
+                let Some(step_cost) = step_cost(grid, &query.rules, &current.position, direction)
+                else {
+                    continue;
+                };
+
+                let new_position = step(&current.position, direction);
+                let new_cost = current.cost_so_far + step_cost;
+
+                if query.exceeds_max_path_cost(new_cost) {
+                    self.over_max_path_cost = true;
+                    continue;
+                }
+
+                if self
+                    .cost_so_far
+                    .get(&new_position)
+                    .is_some_and(|&cost| cost <= new_cost)
+                {
+                    continue;
+                }
+
+                self.cost_so_far.insert(new_position, new_cost);
+                self.came_from.insert(new_position, Some(current.position));
+                self.open_list.push(OpenNode {
+                    position: new_position,
+                    cost: new_cost + query.estimate(&new_position),
+                    cost_so_far: new_cost,
+                });
+                self.stats.record_open_list(self.open_list.len());
+            }
+        }
+
+        if self.over_max_path_cost {
+            Expansion::BudgetExhausted
+        } else {
+            Expansion::Unreachable
+        }
+    }
+
+    // Cost of reaching position from its parent on the grid as it is now, None once the step
+    // is no longer allowed. The start is always reached.
+    fn queued_cost(&self, grid: &dyn PathfindingGrid, position: &Vec3I) -> Option<f32> {
+        let Some(parent) = self.came_from[position] else {
+            return Some(0.0);
+        };
+
+        let direction = Vec3I {
+            x: position.x - parent.x,
+            y: position.y - parent.y,
+            z: position.z - parent.z,
+        };
+        let parent_cost = self.cost_so_far.get(&parent)?;
+        Some(parent_cost + step_cost(grid, &self.query.rules, &parent, &direction)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        grid::grid::VoxelGrid,
+        path::{astar::astar_i, fixture::open_grid, neighborhood::Neighborhood},
+    };
+
+    #[test]
+    fn steps_until_the_path_is_found() {
+        let grid = open_grid(&Vec3I { x: 6, y: 6, z: 6 });
+        let query = PathQuery::new(&Vec3I { x: 0, y: 0, z: 0 }, &Vec3I { x: 5, y: 5, z: 5 })
+            .neighborhood(Neighborhood::Six);
+        let expected = astar_i(&grid, &query).unwrap();
+
+        let mut search = IncrementalSearch::new(&query);
+        let mut steps = 0;
+        let result = loop {
+            steps += 1;
+            match search.step(&grid, 2) {
+                SearchStatus::InProgress => assert!(search.stats().nodes_expanded <= steps * 2),
+                SearchStatus::Found(result) => break result,
+                SearchStatus::Failed(error) => panic!("{error}"),
+            }
+        };
+
+        assert!(steps > 1);
+        assert!(search.is_finished());
+        // Ties between equally cheap nodes may break differently, the cost has to match.
+        assert_eq!(result.cost, expected.cost);
+        assert_eq!(result.path.len(), expected.path.len());
+        assert_eq!(search.step(&grid, 2), SearchStatus::Found(result));
+    }
+
+    #[test]
+    fn picks_up_edits_between_steps() {
+        let mut grid = open_grid(&Vec3I { x: 6, y: 6, z: 6 });
+        let query = PathQuery::new(&Vec3I { x: 0, y: 0, z: 0 }, &Vec3I { x: 5, y: 0, z: 0 });
+
+        let mut search = IncrementalSearch::new(&query);
+        assert_eq!(search.step(&grid, 1), SearchStatus::InProgress);
+
+        grid.delete_cell(5, 0, 0);
+        assert!(matches!(
+            search.step(&grid, usize::MAX),
+            SearchStatus::Failed(PathError::GoalUnreachable { .. })
+        ));
+    }
+
+    #[test]
+    fn checks_queued_nodes_again_after_edits() {
+        let mut grid = open_grid(&Vec3I { x: 6, y: 2, z: 1 });
+        let query = PathQuery::new(&Vec3I { x: 0, y: 0, z: 0 }, &Vec3I { x: 5, y: 0, z: 0 })
+            .neighborhood(Neighborhood::Six);
+
+        let mut search = IncrementalSearch::new(&query);
+        assert_eq!(search.step(&grid, 1), SearchStatus::InProgress);
+
+        // Queued by the expansion of the start.
+        grid.delete_cell(1, 0, 0);
+        let SearchStatus::Found(result) = search.step(&grid, usize::MAX) else {
+            panic!("path not found");
+        };
+
+        assert_eq!(result.cost, 7.0);
+        assert!(result
+            .path
+            .iter()
+            .all(|cell| grid.is_valid_path_node_i(cell)));
+        assert_eq!(result.path, astar_i(&grid, &query).unwrap().path);
+    }
+
+    #[test]
+    fn fails_on_blocked_endpoints() {
+        let grid = open_grid(&Vec3I { x: 6, y: 6, z: 6 });
+        let query = PathQuery::new(&Vec3I { x: 0, y: 0, z: 0 }, &Vec3I { x: 9, y: 0, z: 0 });
+
+        let mut search = IncrementalSearch::new(&query);
+        assert_eq!(
+            search.step(&grid, 1),
+            SearchStatus::Failed(PathError::GoalBlocked)
+        );
+    }
+}
@@ -1,187 +1,705 @@
 use std::{
-    cmp::Ordering,
-    collections::{BinaryHeap, HashMap},
+    collections::{hash_map::Entry, BinaryHeap, HashMap},
+    time::Instant,
 };
 
-use crate::{grid::grid::PathfindingGrid, primitive::vector::Vec3I};
+use crate::{
+    grid::grid::PathfindingGrid,
+    path::{
+        neighborhood::{step, step_length, MovementRules, DIRECTIONS},
+        node::{reconstruct_path, OpenNode},
+        path::PathfindingEngine,
+        query::PathQuery,
+        result::{check_path_endpoints, PathError, PathResult, PathStats},
+    },
+    primitive::{alias::TransformInt, vector::Vec3I},
+};
 
-const JPS_EPSILON: f32 = 1e-6;
+// Jump point search over voxel grids, works with every set of movement rules and non uniform
+// traversal costs.
+//
+// A neighbor of a cell is pruned when the parent of the cell reaches it at most as expensively
+// without passing through the cell, ties are broken in favor of the path whose first step comes
+// first in DIRECTIONS. Pruning only happens inside of open space: cells whose 3x3x3 block holds
+// no blocked cell and a single traversal cost keep the natural neighbors of the direction they
+// were reached from. Every other cell is a jump point expanded in every direction like plain A*
+// does, which keeps the checks made while jumping down to looking up the cells entering the block.
+//
+// Jumps stop after JPS_MAX_JUMP steps so open space is not scanned far away from the goal. Every
+// cell scanned while jumping counts as an expanded node. The returned path contains every cell
+// from start to goal.
+
+const JPS_EPSILON: f32 = 1e-4;
+const JPS_MAX_JUMP: usize = 4;
+
+// Cells of the 3x3x3 block around a cell, indexed by jps_i_block_index.
+type JpsIBlock = [Option<f32>; 27];
+
+const JPS_BLOCK_CENTER: usize = 13;
+const JPS_BLOCK_ORIGIN: Vec3I = Vec3I { x: 0, y: 0, z: 0 };
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Jps;
+
+impl PathfindingEngine for Jps {
+    fn compute_path_i(
+        &self,
+        grid: &dyn PathfindingGrid,
+        query: &PathQuery,
+    ) -> Result<PathResult, PathError> {
+        jps_i(grid, query)
+    }
+}
 
 #[derive(Copy, Clone)]
-struct JpsINode {
-    pub position: Vec3I,
-    pub cost: f32,
+struct JpsIState {
+    position: Vec3I,
+    // Index into DIRECTIONS of the direction the cell was reached from, None for the start.
+    direction: Option<usize>,
+    // Traversal cost of the open block around the cell, None for cells expanded in every
+    // direction.
+    open_cost: Option<f32>,
+    cost: f32,
+    parent: Option<usize>,
 }
 
-impl PartialEq for JpsINode {
-    fn eq(&self, other: &Self) -> bool {
-        self.position == other.position && (self.cost - other.cost).abs() < JPS_EPSILON
-    }
+// Cell a jump stopped at.
+struct JpsIJump {
+    position: Vec3I,
+    cost: f32,
+    open_cost: Option<f32>,
 }
 
-impl Eq for JpsINode {}
-
-impl Ord for JpsINode {
-    fn cmp(&self, other: &Self) -> Ordering {
-        other.cost.partial_cmp(&self.cost).unwrap()
-    }
+struct JpsISearch<'a> {
+    grid: &'a dyn PathfindingGrid,
+    query: &'a PathQuery,
+    natural: [u32; 26],
+    stats: PathStats,
+    // Set once a jump ran out of expanded nodes.
+    budget_exhausted: bool,
 }
 
-impl PartialOrd for JpsINode {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
+pub fn jps_i(grid: &dyn PathfindingGrid, query: &PathQuery) -> Result<PathResult, PathError> {
+    let started = Instant::now();
+    let (start, goal) = (&query.start, &query.goal);
+    check_path_endpoints(grid, start, goal)?;
 
-pub fn jps_i(start: &Vec3I, goal: &Vec3I, grid: &dyn PathfindingGrid) -> Option<Vec<Vec3I>> {
-    let mut open_list = BinaryHeap::new();
-    let mut came_from = HashMap::<Vec3I, Option<Vec3I>>::new();
-    let mut cost_so_far = HashMap::<Vec3I, f32>::new();
-
-    open_list.push(JpsINode {
+    let mut search = JpsISearch::new(grid, query);
+    let mut over_max_path_cost = false;
+    let mut states = vec![JpsIState {
         position: *start,
+        direction: None,
+        open_cost: None,
         cost: 0.0,
-    });
+        parent: None,
+    }];
+    let mut lookup = HashMap::<(Vec3I, Option<usize>), usize>::new();
+    let mut open_list = BinaryHeap::new();
 
-    came_from.insert(*start, None);
-    cost_so_far.insert(*start, 0.0);
+    lookup.insert((*start, None), 0);
+    open_list.push(OpenNode {
+        position: 0,
+        cost: query.estimate(start),
+        cost_so_far: 0.0,
+    });
+    search.stats.record_open_list(open_list.len());
 
     while let Some(current) = open_list.pop() {
-        if current.position == *goal {
-            return Some(jps_i_reconstruct_path(came_from, *goal));
+        let state = states[current.position];
+        if current.cost_so_far > state.cost {
+            continue;
+        }
+
+        if state.position == *goal {
+            let mut stats = search.stats;
+            stats.elapsed = started.elapsed();
+            return Ok(PathResult {
+                path: jps_i_reconstruct_path(&states, current.position),
+                cost: state.cost,
+                stats,
+            });
+        }
+
+        if query.is_expansion_budget_exhausted(&search.stats) {
+            search.budget_exhausted = true;
+            break;
         }
+        search.stats.nodes_expanded += 1;
 
-        for direction in jps_i_directions() {
-            let new_position = current.position;
-            let mut jump_cost = 0.0;
+        let (block, mut successors) = match (state.direction, state.open_cost) {
+            (Some(direction), Some(open_cost)) => {
+                ([Some(open_cost); 27], search.natural[direction])
+            }
+            _ => {
+                let block = search.block(&state.position);
+                (block, search.all_successors(&block))
+            }
+        };
+        while successors != 0 {
+            let direction = successors.trailing_zeros() as usize;
+            successors &= successors - 1;
 
-            loop {
-                if !jps_i_is_valid_position(grid, &new_position) {
+            let Some(jump) = search.jump(&state.position, &block, direction) else {
+                if search.budget_exhausted {
                     break;
                 }
+                continue;
+            };
+            let new_cost = state.cost + jump.cost;
 
-                jump_cost += 1.0; // ToDo: Non uniform jump cost
+            if query.exceeds_max_path_cost(new_cost) {
+                over_max_path_cost = true;
+                continue;
+            }
 
-                if jps_i_is_jump_point(grid, new_position, direction) {
-                    let new_cost = cost_so_far[&current.position] + jump_cost;
+            // Cells expanded in every direction are searched once, whichever direction they are
+            // reached from.
+            let key = (jump.position, jump.open_cost.and(Some(direction)));
+            let index = match lookup.entry(key) {
+                Entry::Occupied(entry) => {
+                    let index = *entry.get();
+                    if new_cost >= states[index].cost {
+                        continue;
+                    }
+                    states[index].direction = Some(direction);
+                    states[index].cost = new_cost;
+                    states[index].parent = Some(current.position);
+                    index
+                }
+                Entry::Vacant(entry) => {
+                    states.push(JpsIState {
+                        position: jump.position,
+                        direction: Some(direction),
+                        open_cost: jump.open_cost,
+                        cost: new_cost,
+                        parent: Some(current.position),
+                    });
+                    *entry.insert(states.len() - 1)
+                }
+            };
+
+            open_list.push(OpenNode {
+                position: index,
+                cost: new_cost + query.estimate(&jump.position),
+                cost_so_far: new_cost,
+            });
+            search.stats.record_open_list(open_list.len());
+        }
 
-                    if !cost_so_far.contains_key(&new_position)
-                        || new_cost < cost_so_far[&new_position]
-                    {
-                        cost_so_far.insert(new_position, new_cost);
-                        let priority = new_cost + jps_i_heuristic(&new_position, &goal);
+        if search.budget_exhausted {
+            break;
+        }
+    }
 
-                        open_list.push(JpsINode {
-                            position: new_position,
-                            cost: priority,
-                        });
+    let mut stats = search.stats;
+    stats.elapsed = started.elapsed();
+    if search.budget_exhausted || over_max_path_cost {
+        Err(PathError::BudgetExhausted { stats })
+    } else {
+        Err(PathError::GoalUnreachable { stats })
+    }
+}
 
-                        came_from.insert(new_position, Some(current.position));
-                    }
-                    break;
-                }
+impl<'a> JpsISearch<'a> {
+    fn new(grid: &'a dyn PathfindingGrid, query: &'a PathQuery) -> Self {
+        let rules = &query.rules;
+        let empty = [Some(1.0); 27];
+        let mut natural = [0; 26];
+        for (direction, natural) in natural.iter_mut().enumerate() {
+            if rules.neighborhood.contains(&DIRECTIONS[direction]) {
+                *natural = jps_i_compute_successors(rules, &empty, direction);
             }
         }
+
+        Self {
+            grid,
+            query,
+            natural,
+            stats: PathStats::default(),
+            budget_exhausted: false,
+        }
     }
 
-    None
+    // Traversal costs of the block around position.
+    fn block(&self, position: &Vec3I) -> JpsIBlock {
+        let mut block = [None; 27];
+        for (index, cell) in block.iter_mut().enumerate() {
+            *cell = self
+                .grid
+                .traversal_cost_i(&step(position, &jps_i_block_offset(index)));
+        }
+        block
+    }
+
+    // Every direction the rules allow stepping into from the block center, as a bit mask over
+    // DIRECTIONS.
+    fn all_successors(&self, block: &JpsIBlock) -> u32 {
+        let mut successors = 0;
+        for (index, direction) in DIRECTIONS.iter().enumerate() {
+            if self
+                .query
+                .rules
+                .allows_step(&JPS_BLOCK_ORIGIN, direction, |cell| {
+                    jps_i_block_enterable(block, cell)
+                })
+            {
+                successors |= 1 << index;
+            }
+        }
+        successors
+    }
+
+    // Whether every cell of the block around position can be entered for open_cost. entered_from
+    // is the direction of the last step when the block around the previous cell was open for the
+    // same cost, only the cells that were outside of it are looked up then.
+    fn is_open(&self, position: &Vec3I, open_cost: f32, entered_from: Option<&Vec3I>) -> bool {
+        (0..27).all(|index| {
+            let offset = jps_i_block_offset(index);
+            entered_from.is_some_and(|direction| jps_i_block_contains(&step(&offset, direction)))
+                || self.grid.traversal_cost_i(&step(position, &offset)) == Some(open_cost)
+        })
+    }
+
+    // Moves from the center of block at position in direction until a jump point is found. None
+    // when the line ends without one or the expansion budget ran out, which sets budget_exhausted.
+    fn jump(&mut self, position: &Vec3I, block: &JpsIBlock, direction: usize) -> Option<JpsIJump> {
+        let step_direction = DIRECTIONS[direction];
+        if !self
+            .query
+            .rules
+            .allows_step(&JPS_BLOCK_ORIGIN, &step_direction, |cell| {
+                jps_i_block_enterable(block, cell)
+            })
+        {
+            return None;
+        }
+
+        // Only cells of a single traversal cost are crossed, any other cost ends the open space.
+        let open_cost = block[jps_i_block_index(&step_direction)]?;
+        let open_block = [Some(open_cost); 27];
+        let step_cost = open_cost * step_length(&step_direction);
+
+        let mut current = *position;
+        let mut cost = 0.0;
+        let mut entered_from = None;
+
+        for _ in 0..JPS_MAX_JUMP {
+            if self.query.is_expansion_budget_exhausted(&self.stats) {
+                self.budget_exhausted = true;
+                return None;
+            }
+
+            current = step(&current, &step_direction);
+            cost += step_cost;
+            self.stats.nodes_expanded += 1;
+
+            let jump_point = |open: bool| {
+                Some(JpsIJump {
+                    position: current,
+                    cost,
+                    open_cost: open.then_some(open_cost),
+                })
+            };
+
+            if current == self.query.goal {
+                return jump_point(true);
+            }
+            if !self.is_open(&current, open_cost, entered_from) {
+                return jump_point(false);
+            }
+            entered_from = Some(&step_direction);
+
+            let mut natural = self.natural[direction] & !(1 << direction);
+            while natural != 0 {
+                let natural_direction = natural.trailing_zeros() as usize;
+                natural &= natural - 1;
+
+                if self
+                    .jump(&current, &open_block, natural_direction)
+                    .is_some()
+                {
+                    return jump_point(true);
+                }
+                if self.budget_exhausted {
+                    return None;
+                }
+            }
+        }
+
+        // Picks the line up again once the search gets back to it.
+        Some(JpsIJump {
+            position: current,
+            cost,
+            open_cost: Some(open_cost),
+        })
+    }
 }
 
-fn jps_i_heuristic(a: &Vec3I, b: &Vec3I) -> f32 {
-    ((a.x - b.x).abs() + (a.y - b.y).abs() + (a.z - b.z).abs()) as f32
+// Neighbors of the block center worth searching when the center was reached from direction, as a
+// bit mask over DIRECTIONS.
+fn jps_i_compute_successors(rules: &MovementRules, block: &JpsIBlock, direction: usize) -> u32 {
+    let Some(center_cost) = block[JPS_BLOCK_CENTER] else {
+        return 0;
+    };
+
+    let incoming = DIRECTIONS[direction];
+    let parent = jps_i_block_index(&Vec3I {
+        x: -incoming.x,
+        y: -incoming.y,
+        z: -incoming.z,
+    });
+    let (distance, first_step) = jps_i_block_paths(rules, block, parent);
+
+    let mut successors = 0;
+    for (index, outgoing) in DIRECTIONS.iter().enumerate() {
+        if !rules.allows_step(&JPS_BLOCK_ORIGIN, outgoing, |cell| {
+            jps_i_block_enterable(block, cell)
+        }) {
+            continue;
+        }
+
+        let neighbor = jps_i_block_index(outgoing);
+        let Some(neighbor_cost) = block[neighbor] else {
+            continue;
+        };
+        if neighbor == parent {
+            continue;
+        }
+
+        // The neighbor is pruned when the parent reaches it at most as expensively without
+        // passing through the center, ties go to paths starting with a preferred direction.
+        let through_center =
+            step_length(&incoming) * center_cost + step_length(outgoing) * neighbor_cost;
+        let tolerance = through_center * JPS_EPSILON;
+        let pruned = distance[neighbor] < through_center - tolerance
+            || (first_step[neighbor] < direction
+                && distance[neighbor] <= through_center + tolerance);
+
+        if !pruned {
+            successors |= 1 << index;
+        }
+    }
+
+    successors
 }
 
-fn jps_i_is_valid_position(grid: &dyn PathfindingGrid, position: &Vec3I) -> bool {
-    grid.is_valid_path_node_i(position)
+// Cheapest paths from parent to every cell of the block that avoid the block center, along with
+// the most preferred first step among the cheapest paths.
+fn jps_i_block_paths(
+    rules: &MovementRules,
+    block: &JpsIBlock,
+    parent: usize,
+) -> ([f32; 27], [usize; 27]) {
+    let mut distance = [f32::INFINITY; 27];
+    let mut first_step = [usize::MAX; 27];
+    let mut visited = [false; 27];
+    visited[parent] = true;
+    visited[JPS_BLOCK_CENTER] = true;
+
+    let parent_offset = jps_i_block_offset(parent);
+    for (index, direction) in DIRECTIONS.iter().enumerate() {
+        if let Some((next, cost)) = jps_i_block_step(rules, block, &parent_offset, direction) {
+            jps_i_relax(&mut distance, &mut first_step, next, cost, index);
+        }
+    }
+
+    while let Some(current) = (0..27)
+        .filter(|&index| !visited[index] && distance[index].is_finite())
+        .min_by(|&a, &b| distance[a].total_cmp(&distance[b]))
+    {
+        visited[current] = true;
+
+        let current_offset = jps_i_block_offset(current);
+        for direction in rules.neighborhood.directions() {
+            if let Some((next, cost)) = jps_i_block_step(rules, block, &current_offset, direction) {
+                if !visited[next] {
+                    let (cost, step) = (distance[current] + cost, first_step[current]);
+                    jps_i_relax(&mut distance, &mut first_step, next, cost, step);
+                }
+            }
+        }
+    }
+
+    (distance, first_step)
 }
 
-fn jps_i_is_obstacle(grid: &dyn PathfindingGrid, position: &Vec3I) -> bool {
-    !grid.is_valid_path_node_i(position)
+fn jps_i_relax(
+    distance: &mut [f32; 27],
+    first_step: &mut [usize; 27],
+    cell: usize,
+    cost: f32,
+    step: usize,
+) {
+    let tolerance = cost * JPS_EPSILON;
+    if cost < distance[cell] - tolerance
+        || (cost <= distance[cell] + tolerance && step < first_step[cell])
+    {
+        distance[cell] = distance[cell].min(cost);
+        first_step[cell] = step;
+    }
 }
 
-fn jps_i_directions() -> Vec<Vec3I> {
-    vec![
-        Vec3I { x: 1, y: 0, z: 0 },
-        Vec3I { x: -1, y: 0, z: 0 },
-        Vec3I { x: 0, y: 1, z: 0 },
-        Vec3I { x: 0, y: -1, z: 0 },
-        Vec3I { x: 0, y: 0, z: 1 },
-        Vec3I { x: 0, y: 0, z: -1 },
-    ]
+// Block cell reached by a single step from offset in direction and the cost of the step, None if
+// the step leaves the block, enters the center or enters a blocked cell.
+fn jps_i_block_step(
+    rules: &MovementRules,
+    block: &JpsIBlock,
+    offset: &Vec3I,
+    direction: &Vec3I,
+) -> Option<(usize, f32)> {
+    if !rules.allows_step(offset, direction, |cell| jps_i_block_enterable(block, cell)) {
+        return None;
+    }
+
+    let next = jps_i_block_index(&step(offset, direction));
+    if next == JPS_BLOCK_CENTER {
+        return None;
+    }
+
+    Some((next, block[next]? * step_length(direction)))
 }
 
-fn jps_i_is_jump_point(grid: &dyn PathfindingGrid, position: Vec3I, direction: Vec3I) -> bool {
-    let left = Vec3I {
-        x: -direction.z,
-        y: direction.y,
-        z: direction.x,
-    };
-    let right = Vec3I {
-        x: direction.z,
-        y: direction.y,
-        z: -direction.x,
-    };
+fn jps_i_block_enterable(block: &JpsIBlock, offset: &Vec3I) -> bool {
+    jps_i_block_contains(offset) && block[jps_i_block_index(offset)].is_some()
+}
 
-    let current = position;
+fn jps_i_block_contains(offset: &Vec3I) -> bool {
+    offset.x.abs() <= 1 && offset.y.abs() <= 1 && offset.z.abs() <= 1
+}
 
-    jps_i_has_forced_neighors(grid, current, direction)
-        || jps_i_has_forced_neighors(grid, current, left)
-        || jps_i_has_forced_neighors(grid, current, right)
+fn jps_i_block_index(offset: &Vec3I) -> usize {
+    ((offset.x + 1) + (offset.y + 1) * 3 + (offset.z + 1) * 9) as usize
 }
 
-fn jps_i_move_in_direction(position: Vec3I, direction: Vec3I) -> Vec3I {
+fn jps_i_block_offset(index: usize) -> Vec3I {
+    let index = index as TransformInt;
     Vec3I {
-        x: position.x + direction.x,
-        y: position.y + direction.y,
-        z: position.z + direction.z,
+        x: index % 3 - 1,
+        y: index / 3 % 3 - 1,
+        z: index / 9 - 1,
     }
 }
 
-fn jps_i_has_forced_neighors(
-    grid: &dyn PathfindingGrid,
-    position: Vec3I,
-    direction: Vec3I,
-) -> bool {
-    let orthogonal_dirs = [
-        Vec3I {
-            x: -direction.z,
-            y: 0,
-            z: direction.x,
+fn jps_i_reconstruct_path(states: &[JpsIState], goal: usize) -> Vec<Vec3I> {
+    let jump_points = reconstruct_path(goal, |index| states[index].parent);
+
+    let mut path = vec![states[jump_points[0]].position];
+    for &index in &jump_points[1..] {
+        let state = states[index];
+        let direction = DIRECTIONS[state.direction.unwrap()];
+
+        let mut current = *path.last().unwrap();
+        while current != state.position {
+            current = step(&current, &direction);
+            path.push(current);
+        }
+    }
+
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        grid::{grid::VoxelGrid, voxel::dynamic_sized_voxel_grid::DynamicSizedVoxelGrid},
+        path::{
+            astar::astar_i,
+            cost::Traversable,
+            neighborhood::{step_cost, CornerCutting, Neighborhood},
         },
+    };
+
+    #[derive(Debug, Clone, Copy)]
+    struct Weighted(f32);
+
+    impl Default for Weighted {
+        fn default() -> Self {
+            Self(1.0)
+        }
+    }
+
+    impl Traversable for Weighted {
+        fn traversal_cost(&self) -> f32 {
+            self.0
+        }
+    }
+
+    // Small deterministic generator so failures can be reproduced.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self) -> u64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            self.0 >> 33
+        }
+    }
+
+    const EXTENTS: Vec3I = Vec3I { x: 9, y: 9, z: 9 };
+
+    fn random_grid(random: &mut Lcg, weighted: bool) -> DynamicSizedVoxelGrid<Weighted> {
+        let mut grid = DynamicSizedVoxelGrid::new(&Vec3I { x: 0, y: 0, z: 0 }, &EXTENTS);
+        let cells: Vec<Vec3I> = grid.bounds_as_cuboid().iter_cells().collect();
+
+        for position in cells {
+            if random.next() % 100 < 70 {
+                let cost = if weighted {
+                    [1.0, 1.0, 1.0, 2.5, 6.0][random.next() as usize % 5]
+                } else {
+                    1.0
+                };
+                grid.create_cell(position.x, position.y, position.z, &Weighted(cost));
+            }
+        }
+
+        grid
+    }
+
+    fn random_cell(random: &mut Lcg) -> Vec3I {
         Vec3I {
-            x: direction.z,
-            y: 0,
-            z: -direction.x,
-        },
-    ];
+            x: (random.next() % EXTENTS.x as u64) as TransformInt,
+            y: (random.next() % EXTENTS.y as u64) as TransformInt,
+            z: (random.next() % EXTENTS.z as u64) as TransformInt,
+        }
+    }
 
-    for &ortho in orthogonal_dirs.iter() {
-        let next_pos = jps_i_move_in_direction(position, direction);
-        let check_pos = jps_i_move_in_direction(next_pos, ortho);
+    fn path_cost(grid: &dyn PathfindingGrid, rules: &MovementRules, path: &[Vec3I]) -> f32 {
+        path.windows(2)
+            .map(|pair| {
+                let direction = Vec3I {
+                    x: pair[1].x - pair[0].x,
+                    y: pair[1].y - pair[0].y,
+                    z: pair[1].z - pair[0].z,
+                };
+                step_cost(grid, rules, &pair[0], &direction).expect("path takes a forbidden step")
+            })
+            .sum()
+    }
 
-        if !jps_i_is_valid_position(grid, &check_pos) {
-            continue;
+    // Plain A* is the reference for optimal path costs.
+    fn check_against_astar(rules: MovementRules, weighted: bool, seed: u64) {
+        let mut random = Lcg(seed);
+
+        for _ in 0..6 {
+            let grid = random_grid(&mut random, weighted);
+
+            for _ in 0..6 {
+                let start = random_cell(&mut random);
+                let goal = random_cell(&mut random);
+
+                let expected = astar_i(&grid, &PathQuery::new(&start, &goal).rules(rules));
+                let result = jps_i(&grid, &PathQuery::new(&start, &goal).rules(rules));
+
+                match (expected, result) {
+                    (Err(expected), Err(error)) => assert_eq!(
+                        std::mem::discriminant(&expected),
+                        std::mem::discriminant(&error)
+                    ),
+                    (Ok(expected), Ok(result)) => {
+                        let path = result.path;
+                        assert_eq!(path.first(), Some(&start));
+                        assert_eq!(path.last(), Some(&goal));
+                        let cost = path_cost(&grid, &rules, &path);
+                        assert!((cost - result.cost).abs() <= cost * 1e-4);
+                        let expected = expected.cost;
+                        assert!(
+                            (cost - expected).abs() <= expected * 1e-4,
+                            "{start} -> {goal}: {cost} != {expected}"
+                        );
+                    }
+                    (expected, path) => panic!("{start} -> {goal}: {expected:?} != {path:?}"),
+                }
+            }
         }
+    }
 
-        if jps_i_is_obstacle(grid, &check_pos)
-            && !jps_i_is_obstacle(grid, &jps_i_move_in_direction(position, ortho))
-        {
-            return true;
+    #[test]
+    fn matches_optimal_cost_with_six_neighbors() {
+        let rules = MovementRules::new(Neighborhood::Six, CornerCutting::Allow);
+        check_against_astar(rules, false, 1);
+        check_against_astar(rules, true, 2);
+    }
+
+    #[test]
+    fn matches_optimal_cost_with_eighteen_neighbors() {
+        for corner_cutting in [CornerCutting::Allow, CornerCutting::Forbid] {
+            let rules = MovementRules::new(Neighborhood::Eighteen, corner_cutting);
+            check_against_astar(rules, false, 3);
+            check_against_astar(rules, true, 4);
         }
     }
 
-    false
-}
+    #[test]
+    fn matches_optimal_cost_with_twenty_six_neighbors() {
+        for corner_cutting in [CornerCutting::Allow, CornerCutting::Forbid] {
+            let rules = MovementRules::new(Neighborhood::TwentySix, corner_cutting);
+            check_against_astar(rules, false, 5);
+            check_against_astar(rules, true, 6);
+        }
+    }
+
+    #[test]
+    fn natural_neighbors_come_after_their_direction() {
+        let grid = DynamicSizedVoxelGrid::<Weighted>::new(&EXTENTS, &EXTENTS);
+
+        for neighborhood in [
+            Neighborhood::Six,
+            Neighborhood::Eighteen,
+            Neighborhood::TwentySix,
+        ] {
+            let query = PathQuery::new(&EXTENTS, &EXTENTS)
+                .rules(MovementRules::new(neighborhood, CornerCutting::Allow));
+            let search = JpsISearch::new(&grid, &query);
+
+            for (direction, &natural) in search.natural.iter().enumerate() {
+                if neighborhood.contains(&DIRECTIONS[direction]) {
+                    assert_ne!(natural & (1 << direction), 0);
+                    assert_eq!(natural & ((1 << direction) - 1), 0);
+                }
+            }
+        }
+    }
 
-fn jps_i_reconstruct_path(came_from: HashMap<Vec3I, Option<Vec3I>>, goal: Vec3I) -> Vec<Vec3I> {
-    let mut current = goal;
-    let mut path = vec![current];
+    #[test]
+    fn charges_scanned_cells_against_the_budget() {
+        let mut grid =
+            DynamicSizedVoxelGrid::new(&Vec3I { x: 0, y: 0, z: 0 }, &Vec3I { x: 32, y: 1, z: 1 });
+        for x in 0..32 {
+            grid.create_cell(x, 0, 0, &Weighted(1.0));
+        }
 
-    while let Some(&Some(prev)) = came_from.get(&current) {
-        current = prev;
-        path.push(current);
+        let query = PathQuery::new(&Vec3I { x: 0, y: 0, z: 0 }, &Vec3I { x: 31, y: 0, z: 0 })
+            .neighborhood(Neighborhood::Six);
+        let nodes_expanded = jps_i(&grid, &query).unwrap().stats.nodes_expanded;
+        assert!(nodes_expanded > 31);
+
+        let error = jps_i(&grid, &query.clone().max_expanded_nodes(16)).unwrap_err();
+        assert!(matches!(error, PathError::BudgetExhausted { .. }));
+        assert_eq!(error.stats().unwrap().nodes_expanded, 16);
+        assert!(jps_i(&grid, &query.max_expanded_nodes(nodes_expanded)).is_ok());
     }
 
-    path.reverse();
-    path
+    #[test]
+    fn avoids_expensive_cells_when_cheaper_detour_exists() {
+        let mut grid =
+            DynamicSizedVoxelGrid::new(&Vec3I { x: 0, y: 0, z: 0 }, &Vec3I { x: 5, y: 2, z: 1 });
+        for x in 0..5 {
+            grid.create_cell(x, 0, 0, &Weighted(1.0));
+            grid.create_cell(x, 1, 0, &Weighted(1.0));
+        }
+        grid.update_cell(2, 0, 0, &Weighted(10.0));
+
+        let start = Vec3I { x: 0, y: 0, z: 0 };
+        let goal = Vec3I { x: 4, y: 0, z: 0 };
+        let rules = MovementRules::new(Neighborhood::Six, CornerCutting::Allow);
+        let path = jps_i(&grid, &PathQuery::new(&start, &goal).rules(rules))
+            .unwrap()
+            .path;
+
+        assert!(!path.contains(&Vec3I { x: 2, y: 0, z: 0 }));
+        assert_eq!(path.len(), 7);
+    }
 }
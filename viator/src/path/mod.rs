@@ -1,2 +1,20 @@
+pub mod astar;
+pub mod batch;
+pub mod bidirectional;
+pub mod cost;
+pub mod dstar;
+#[cfg(test)]
+pub mod fixture;
+pub mod flow;
+pub mod hierarchical;
+pub mod incremental;
 pub mod jps;
+pub mod neighborhood;
+pub mod node;
+#[allow(clippy::module_inception)]
 pub mod path;
+pub mod query;
+pub mod reachable;
+pub mod result;
+pub mod service;
+pub mod theta;
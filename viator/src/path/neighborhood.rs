@@ -0,0 +1,197 @@
+use crate::{
+    grid::grid::PathfindingGrid,
+    primitive::{alias::TransformInt, vector::Vec3I},
+};
+
+// Set of neighbors an agent may move to from a cell in a single step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Neighborhood {
+    // Face neighbors only.
+    Six,
+    // Face and edge neighbors.
+    Eighteen,
+    // Face, edge and corner neighbors.
+    #[default]
+    TwentySix,
+}
+
+const fn direction(x: TransformInt, y: TransformInt, z: TransformInt) -> Vec3I {
+    Vec3I { x, y, z }
+}
+
+// Every direction to a neighboring cell, corners first, then edges, then faces. Pathfinders that
+// have to break ties between equally good moves prefer the direction listed first.
+pub const DIRECTIONS: [Vec3I; 26] = [
+    direction(1, 1, 1),
+    direction(-1, 1, 1),
+    direction(1, -1, 1),
+    direction(-1, -1, 1),
+    direction(1, 1, -1),
+    direction(-1, 1, -1),
+    direction(1, -1, -1),
+    direction(-1, -1, -1),
+    direction(1, 1, 0),
+    direction(-1, 1, 0),
+    direction(1, -1, 0),
+    direction(-1, -1, 0),
+    direction(1, 0, 1),
+    direction(-1, 0, 1),
+    direction(1, 0, -1),
+    direction(-1, 0, -1),
+    direction(0, 1, 1),
+    direction(0, -1, 1),
+    direction(0, 1, -1),
+    direction(0, -1, -1),
+    direction(1, 0, 0),
+    direction(-1, 0, 0),
+    direction(0, 1, 0),
+    direction(0, -1, 0),
+    direction(0, 0, 1),
+    direction(0, 0, -1),
+];
+
+impl Neighborhood {
+    pub fn directions(&self) -> &'static [Vec3I] {
+        match self {
+            Neighborhood::Six => &DIRECTIONS[20..],
+            Neighborhood::Eighteen => &DIRECTIONS[8..],
+            Neighborhood::TwentySix => &DIRECTIONS,
+        }
+    }
+
+    pub fn contains(&self, direction: &Vec3I) -> bool {
+        let axes = axis_count(direction);
+        match self {
+            Neighborhood::Six => axes == 1,
+            Neighborhood::Eighteen => axes == 1 || axes == 2,
+            Neighborhood::TwentySix => axes >= 1,
+        }
+    }
+
+    // Length of the shortest path between a and b on an empty grid with a traversal cost of 1.0
+    // everywhere, used as the heuristic of the pathfinders.
+    pub fn distance(&self, a: &Vec3I, b: &Vec3I) -> f32 {
+        let mut delta = [
+            (a.x - b.x).abs() as f32,
+            (a.y - b.y).abs() as f32,
+            (a.z - b.z).abs() as f32,
+        ];
+        delta.sort_by(|a, b| b.total_cmp(a));
+        let [max, mid, min] = delta;
+
+        match self {
+            Neighborhood::Six => max + mid + min,
+            Neighborhood::Eighteen => {
+                // Edge steps cover two axes at once, use as many of them as possible.
+                if max >= mid + min {
+                    (max - mid - min) + (mid + min) * std::f32::consts::SQRT_2
+                } else {
+                    let sum = max + mid + min;
+                    (sum / 2.0).floor() * std::f32::consts::SQRT_2 + sum % 2.0
+                }
+            }
+            Neighborhood::TwentySix => {
+                (max - mid) + (mid - min) * std::f32::consts::SQRT_2 + min * SQRT_3
+            }
+        }
+    }
+}
+
+const SQRT_3: f32 = 1.732_050_8;
+
+// Whether a step to an edge or corner neighbor may pass alongside cells that can not be entered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CornerCutting {
+    #[default]
+    Allow,
+    // Every face and edge neighbor the step passes alongside has to be enterable.
+    Forbid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct MovementRules {
+    pub neighborhood: Neighborhood,
+    pub corner_cutting: CornerCutting,
+}
+
+impl MovementRules {
+    pub fn new(neighborhood: Neighborhood, corner_cutting: CornerCutting) -> Self {
+        Self {
+            neighborhood,
+            corner_cutting,
+        }
+    }
+
+    // Whether a step from position in direction is allowed, is_enterable reports whether a cell
+    // can be entered.
+    pub fn allows_step(
+        &self,
+        position: &Vec3I,
+        direction: &Vec3I,
+        is_enterable: impl Fn(&Vec3I) -> bool,
+    ) -> bool {
+        if !self.neighborhood.contains(direction) || !is_enterable(&step(position, direction)) {
+            return false;
+        }
+
+        match self.corner_cutting {
+            CornerCutting::Allow => true,
+            CornerCutting::Forbid => sub_directions(direction)
+                .all(|sub_direction| is_enterable(&step(position, &sub_direction))),
+        }
+    }
+}
+
+// Every direction made of a proper, non empty subset of the axes of direction.
+fn sub_directions(direction: &Vec3I) -> impl Iterator<Item = Vec3I> {
+    let direction = *direction;
+    (1..7).filter_map(move |mask: u8| {
+        let sub_direction = Vec3I {
+            x: if mask & 1 != 0 { direction.x } else { 0 },
+            y: if mask & 2 != 0 { direction.y } else { 0 },
+            z: if mask & 4 != 0 { direction.z } else { 0 },
+        };
+        let axes = axis_count(&sub_direction);
+        (axes > 0 && axes < axis_count(&direction)).then_some(sub_direction)
+    })
+}
+
+fn axis_count(direction: &Vec3I) -> usize {
+    [direction.x, direction.y, direction.z]
+        .iter()
+        .filter(|&&axis| axis != 0)
+        .count()
+}
+
+// Length of a single step in direction, 1 for faces, √2 for edges and √3 for corners.
+pub fn step_length(direction: &Vec3I) -> f32 {
+    match axis_count(direction) {
+        1 => 1.0,
+        2 => std::f32::consts::SQRT_2,
+        3 => SQRT_3,
+        _ => 0.0,
+    }
+}
+
+pub fn step(position: &Vec3I, direction: &Vec3I) -> Vec3I {
+    Vec3I {
+        x: position.x + direction.x,
+        y: position.y + direction.y,
+        z: position.z + direction.z,
+    }
+}
+
+// Cost of a single step from position in direction, None if the rules do not allow the step.
+pub fn step_cost(
+    grid: &dyn PathfindingGrid,
+    rules: &MovementRules,
+    position: &Vec3I,
+    direction: &Vec3I,
+) -> Option<f32> {
+    if !rules.allows_step(position, direction, |cell| grid.is_valid_path_node_i(cell)) {
+        return None;
+    }
+
+    let cost = grid.traversal_cost_i(&step(position, direction))?;
+    Some(cost * step_length(direction))
+}
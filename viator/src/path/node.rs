@@ -0,0 +1,51 @@
+use std::cmp::Ordering;
+
+use crate::primitive::vector::Vec3I;
+
+// Open list entry shared by the best first searches. Position is usually the cell of the node,
+// searches keeping their nodes in a table store the index of the node instead.
+#[derive(Copy, Clone)]
+pub struct OpenNode<P = Vec3I> {
+    pub position: P,
+    // Cost so far plus the estimate towards the goal.
+    pub cost: f32,
+    pub cost_so_far: f32,
+}
+
+impl<P> PartialEq for OpenNode<P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<P> Eq for OpenNode<P> {}
+
+impl<P> Ord for OpenNode<P> {
+    // Cheapest first, ties go to the node furthest along its path.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then(self.cost_so_far.total_cmp(&other.cost_so_far))
+    }
+}
+
+impl<P> PartialOrd for OpenNode<P> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Follows parent from goal back to the node without one, returns the nodes from there to goal.
+pub fn reconstruct_path<P: Copy>(goal: P, mut parent: impl FnMut(P) -> Option<P>) -> Vec<P> {
+    let mut current = goal;
+    let mut path = vec![current];
+
+    while let Some(previous) = parent(current) {
+        current = previous;
+        path.push(current);
+    }
+
+    path.reverse();
+    path
+}
@@ -1,3 +1,135 @@
+use std::any::Any;
+
+use crate::{
+    grid::grid::PathfindingGrid,
+    path::{
+        astar::AStar,
+        bidirectional::Bidirectional,
+        jps::Jps,
+        query::PathQuery,
+        result::{PathError, PathResult},
+        theta::{LazyThetaStar, ThetaStar},
+    },
+};
+
+// Computes paths on any pathfinding grid, implement it to plug custom engines into
+// PathfindingGrid::compute_path_i. Engines are expected to honor the budgets, heuristic and
+// movement rules of the query.
+pub trait PathfindingEngine: Any + Send + Sync {
+    fn compute_path_i(
+        &self,
+        grid: &dyn PathfindingGrid,
+        query: &PathQuery,
+    ) -> Result<PathResult, PathError>;
+}
+
+// Engines shipped with viator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PathfindingType {
+    AStar,
     Jps,
+    // Any angle paths, only the waypoints are returned.
+    ThetaStar,
+    LazyThetaStar,
+    // Searches from start and goal at the same time.
+    Bidirectional,
+}
+
+impl PathfindingEngine for PathfindingType {
+    fn compute_path_i(
+        &self,
+        grid: &dyn PathfindingGrid,
+        query: &PathQuery,
+    ) -> Result<PathResult, PathError> {
+        match self {
+            PathfindingType::AStar => AStar.compute_path_i(grid, query),
+            PathfindingType::Jps => Jps.compute_path_i(grid, query),
+            PathfindingType::ThetaStar => ThetaStar.compute_path_i(grid, query),
+            PathfindingType::LazyThetaStar => LazyThetaStar.compute_path_i(grid, query),
+            PathfindingType::Bidirectional => Bidirectional.compute_path_i(grid, query),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        grid::{grid::VoxelGrid, voxel::dynamic_sized_voxel_grid::DynamicSizedVoxelGrid},
+        path::{
+            neighborhood::{step, Neighborhood},
+            result::{check_path_endpoints, PathStats},
+        },
+        primitive::cell::Cell,
+        primitive::vector::Vec3I,
+    };
+
+    // Walks straight along the x axis, gives up on anything else.
+    struct StraightLine;
+
+    impl PathfindingEngine for StraightLine {
+        fn compute_path_i(
+            &self,
+            grid: &dyn PathfindingGrid,
+            query: &PathQuery,
+        ) -> Result<PathResult, PathError> {
+            let (start, goal) = (&query.start, &query.goal);
+            check_path_endpoints(grid, start, goal)?;
+
+            let stats = PathStats::default();
+            if start.y != goal.y || start.z != goal.z {
+                return Err(PathError::GoalUnreachable { stats });
+            }
+
+            let direction = Vec3I {
+                x: (goal.x - start.x).signum(),
+                y: 0,
+                z: 0,
+            };
+
+            let mut path = vec![*start];
+            while path.last() != Some(goal) {
+                let next = step(&path[path.len() - 1], &direction);
+                if !grid.is_valid_path_node_i(&next) {
+                    return Err(PathError::GoalUnreachable { stats });
+                }
+                path.push(next);
+            }
+
+            Ok(PathResult {
+                cost: (path.len() - 1) as f32,
+                path,
+                stats,
+            })
+        }
+    }
+
+    #[test]
+    fn grids_accept_custom_engines() {
+        let mut grid =
+            DynamicSizedVoxelGrid::new(&Vec3I { x: 0, y: 0, z: 0 }, &Vec3I { x: 4, y: 2, z: 1 });
+        for x in 0..4 {
+            grid.create_cell(x, 0, 0, &Cell);
+        }
+
+        let start = Vec3I { x: 0, y: 0, z: 0 };
+        let goal = Vec3I { x: 3, y: 0, z: 0 };
+
+        let query = PathQuery::new(&start, &goal).neighborhood(Neighborhood::Six);
+        let path = grid
+            .compute_path_i(&query.clone().engine(StraightLine))
+            .unwrap()
+            .path;
+        assert_eq!(path.len(), 4);
+
+        for engine in [
+            PathfindingType::AStar,
+            PathfindingType::Jps,
+            PathfindingType::Bidirectional,
+        ] {
+            let result = grid.compute_path_i(&query.clone().engine(engine)).unwrap();
+            assert_eq!(result.path, path);
+            assert_eq!(result.cost, 3.0);
+        }
+    }
 }
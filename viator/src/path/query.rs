@@ -0,0 +1,205 @@
+use std::{fmt, sync::Arc};
+
+use crate::{
+    path::{
+        neighborhood::{CornerCutting, MovementRules, Neighborhood},
+        path::{PathfindingEngine, PathfindingType},
+        result::PathStats,
+    },
+    primitive::vector::Vec3I,
+};
+
+// Estimate of the remaining cost to the goal used to order the search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Heuristic {
+    // Shortest path on an empty grid under the movement rules of the query, exact for a traversal
+    // cost of 1.0 everywhere.
+    #[default]
+    Octile,
+    Manhattan,
+    Euclidean,
+    // No estimate at all, the search degrades to Dijkstra.
+    Zero,
+}
+
+impl Heuristic {
+    pub fn estimate(&self, neighborhood: Neighborhood, from: &Vec3I, to: &Vec3I) -> f32 {
+        let dx = (from.x - to.x).abs() as f32;
+        let dy = (from.y - to.y).abs() as f32;
+        let dz = (from.z - to.z).abs() as f32;
+
+        match self {
+            Heuristic::Octile => neighborhood.distance(from, to),
+            Heuristic::Manhattan => dx + dy + dz,
+            Heuristic::Euclidean => (dx * dx + dy * dy + dz * dz).sqrt(),
+            Heuristic::Zero => 0.0,
+        }
+    }
+}
+
+// Everything a single path search needs, built with the chained setters below:
+//
+// PathQuery::new(&start, &goal)
+//     .engine(PathfindingType::Jps)
+//     .neighborhood(Neighborhood::Six)
+//     .max_expanded_nodes(10_000)
+//
+// Heuristics overestimating the cost (Manhattan with diagonal steps, weights above 1.0) trade
+// optimality for fewer expanded nodes.
+#[derive(Clone)]
+pub struct PathQuery {
+    pub start: Vec3I,
+    pub goal: Vec3I,
+    pub engine: Arc<dyn PathfindingEngine>,
+    pub heuristic: Heuristic,
+    pub heuristic_weight: f32,
+    // The search gives up once it expanded this many nodes without reaching the goal.
+    pub max_expanded_nodes: Option<usize>,
+    // Paths costing more than this are not searched.
+    pub max_path_cost: Option<f32>,
+    pub rules: MovementRules,
+}
+
+impl PathQuery {
+    pub fn new(start: &Vec3I, goal: &Vec3I) -> Self {
+        Self {
+            start: *start,
+            goal: *goal,
+            engine: Arc::new(PathfindingType::AStar),
+            heuristic: Heuristic::default(),
+            heuristic_weight: 1.0,
+            max_expanded_nodes: None,
+            max_path_cost: None,
+            rules: MovementRules::default(),
+        }
+    }
+
+    pub fn engine(mut self, engine: impl PathfindingEngine + 'static) -> Self {
+        self.engine = Arc::new(engine);
+        self
+    }
+
+    pub fn shared_engine(mut self, engine: Arc<dyn PathfindingEngine>) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    pub fn heuristic(mut self, heuristic: Heuristic) -> Self {
+        self.heuristic = heuristic;
+        self
+    }
+
+    pub fn heuristic_weight(mut self, heuristic_weight: f32) -> Self {
+        self.heuristic_weight = heuristic_weight;
+        self
+    }
+
+    pub fn max_expanded_nodes(mut self, max_expanded_nodes: usize) -> Self {
+        self.max_expanded_nodes = Some(max_expanded_nodes);
+        self
+    }
+
+    pub fn max_path_cost(mut self, max_path_cost: f32) -> Self {
+        self.max_path_cost = Some(max_path_cost);
+        self
+    }
+
+    pub fn rules(mut self, rules: MovementRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    pub fn neighborhood(mut self, neighborhood: Neighborhood) -> Self {
+        self.rules.neighborhood = neighborhood;
+        self
+    }
+
+    pub fn corner_cutting(mut self, corner_cutting: CornerCutting) -> Self {
+        self.rules.corner_cutting = corner_cutting;
+        self
+    }
+
+    // Weighted estimate of the cost from position to the goal.
+    pub fn estimate(&self, position: &Vec3I) -> f32 {
+        self.heuristic
+            .estimate(self.rules.neighborhood, position, &self.goal)
+            * self.heuristic_weight
+    }
+
+    pub fn is_expansion_budget_exhausted(&self, stats: &PathStats) -> bool {
+        self.max_expanded_nodes
+            .is_some_and(|max_expanded_nodes| stats.nodes_expanded >= max_expanded_nodes)
+    }
+
+    pub fn exceeds_max_path_cost(&self, cost: f32) -> bool {
+        self.max_path_cost
+            .is_some_and(|max_path_cost| cost > max_path_cost)
+    }
+}
+
+impl fmt::Debug for PathQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PathQuery")
+            .field("start", &self.start)
+            .field("goal", &self.goal)
+            .field("heuristic", &self.heuristic)
+            .field("heuristic_weight", &self.heuristic_weight)
+            .field("max_expanded_nodes", &self.max_expanded_nodes)
+            .field("max_path_cost", &self.max_path_cost)
+            .field("rules", &self.rules)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        grid::grid::PathfindingGrid,
+        path::{fixture::open_grid, result::PathError},
+    };
+
+    #[test]
+    fn budgets_stop_the_search() {
+        let grid = open_grid(&Vec3I { x: 8, y: 8, z: 1 });
+        let query = PathQuery::new(&Vec3I { x: 0, y: 0, z: 0 }, &Vec3I { x: 7, y: 7, z: 0 })
+            .neighborhood(Neighborhood::Six);
+
+        for engine in [PathfindingType::AStar, PathfindingType::Jps] {
+            let query = query.clone().engine(engine);
+            assert_eq!(grid.compute_path_i(&query).unwrap().cost, 14.0);
+
+            let error = grid
+                .compute_path_i(&query.clone().max_expanded_nodes(1))
+                .unwrap_err();
+            assert!(matches!(error, PathError::BudgetExhausted { .. }));
+            assert_eq!(error.stats().unwrap().nodes_expanded, 1);
+
+            let error = grid
+                .compute_path_i(&query.clone().max_path_cost(13.0))
+                .unwrap_err();
+            assert!(matches!(error, PathError::BudgetExhausted { .. }));
+
+            assert!(grid.compute_path_i(&query.max_path_cost(14.0)).is_ok());
+        }
+    }
+
+    #[test]
+    fn heuristics_trade_expanded_nodes_for_optimality() {
+        let grid = open_grid(&Vec3I { x: 8, y: 8, z: 1 });
+        let query = PathQuery::new(&Vec3I { x: 0, y: 0, z: 0 }, &Vec3I { x: 7, y: 3, z: 0 })
+            .neighborhood(Neighborhood::Eighteen);
+
+        let octile = grid.compute_path_i(&query).unwrap();
+        let dijkstra = grid
+            .compute_path_i(&query.clone().heuristic(Heuristic::Zero))
+            .unwrap();
+        let weighted = grid
+            .compute_path_i(&query.clone().heuristic_weight(3.0))
+            .unwrap();
+
+        assert!((octile.cost - dijkstra.cost).abs() < 1e-4);
+        assert!(dijkstra.stats.nodes_expanded > octile.stats.nodes_expanded);
+        assert!(weighted.stats.nodes_expanded <= octile.stats.nodes_expanded);
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use crate::{
+    grid::grid::PathfindingGrid,
+    path::{
+        flow::{flow_dijkstra_i, FlowCell},
+        neighborhood::MovementRules,
+    },
+    primitive::vector::Vec3I,
+};
+
+// Dijkstra map, the cost of reaching every cell from the closest of one or more starts. Bounded
+// by a maximum cost it answers isochrone queries (movement ranges, the safest cell within reach),
+// only the reached cells are stored.
+
+#[derive(Debug, Clone, Default)]
+pub struct DijkstraMap {
+    // The direction of every cell is the step taken into it.
+    cells: HashMap<Vec3I, FlowCell>,
+}
+
+impl DijkstraMap {
+    pub fn new(
+        grid: &dyn PathfindingGrid,
+        starts: &[Vec3I],
+        rules: &MovementRules,
+        max_cost: Option<f32>,
+    ) -> Self {
+        Self {
+            cells: flow_dijkstra_i(grid, starts, rules, max_cost, false),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn contains(&self, position: &Vec3I) -> bool {
+        self.cells.contains_key(position)
+    }
+
+    // None if position was not reached.
+    pub fn cost(&self, position: &Vec3I) -> Option<f32> {
+        self.cells.get(position).map(|cell| cell.distance)
+    }
+
+    // Every cell from the closest start to position, both included.
+    pub fn path_to(&self, position: &Vec3I) -> Option<Vec<Vec3I>> {
+        let mut current = *self.cells.get(position)?;
+        let mut path = vec![*position];
+
+        while let Some(direction) = current.direction {
+            let last = path[path.len() - 1];
+            let previous = Vec3I {
+                x: last.x - direction.x,
+                y: last.y - direction.y,
+                z: last.z - direction.z,
+            };
+            current = self.cells[&previous];
+            path.push(previous);
+        }
+
+        path.reverse();
+        Some(path)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Vec3I, f32)> {
+        self.cells
+            .iter()
+            .map(|(position, cell)| (position, cell.distance))
+    }
+}
+
+// Every cell reachable from start at a cost of at most max_cost.
+pub fn reachable_within_i(
+    grid: &dyn PathfindingGrid,
+    start: &Vec3I,
+    rules: &MovementRules,
+    max_cost: f32,
+) -> DijkstraMap {
+    DijkstraMap::new(grid, &[*start], rules, Some(max_cost))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        grid::grid::VoxelGrid,
+        path::{
+            astar::astar_i,
+            fixture::open_grid,
+            neighborhood::{CornerCutting, Neighborhood},
+            query::PathQuery,
+        },
+    };
+
+    #[test]
+    fn reaches_every_cell_within_the_budget() {
+        let mut grid = open_grid(&Vec3I { x: 7, y: 7, z: 3 });
+        for z in 0..3 {
+            grid.delete_cell(3, 2, z);
+            grid.delete_cell(3, 3, z);
+            grid.delete_cell(3, 4, z);
+        }
+
+        let start = Vec3I { x: 1, y: 3, z: 1 };
+        let rules = MovementRules::default();
+        let reachable = reachable_within_i(&grid, &start, &rules, 3.5);
+        assert_eq!(reachable.cost(&start), Some(0.0));
+
+        for position in grid.bounds_as_cuboid().iter_cells() {
+            let expected = astar_i(&grid, &PathQuery::new(&start, &position))
+                .ok()
+                .map(|result| result.cost)
+                .filter(|&cost| cost <= 3.5);
+
+            match (reachable.cost(&position), expected) {
+                (Some(cost), Some(expected)) => {
+                    assert!((cost - expected).abs() < 1e-4);
+                    let path = reachable.path_to(&position).unwrap();
+                    assert_eq!(path[0], start);
+                    assert_eq!(path[path.len() - 1], position);
+                }
+                (cost, expected) => assert_eq!(cost, expected),
+            }
+        }
+    }
+
+    #[test]
+    fn measures_from_the_closest_start() {
+        let grid = open_grid(&Vec3I { x: 7, y: 7, z: 3 });
+        let rules = MovementRules::new(Neighborhood::Six, CornerCutting::Allow);
+        let starts = [Vec3I { x: 0, y: 0, z: 0 }, Vec3I { x: 6, y: 6, z: 2 }];
+        let map = DijkstraMap::new(&grid, &starts, &rules, None);
+
+        assert_eq!(map.len(), 7 * 7 * 3);
+        assert_eq!(map.cost(&Vec3I { x: 5, y: 6, z: 2 }), Some(1.0));
+        assert_eq!(map.cost(&Vec3I { x: 1, y: 1, z: 0 }), Some(2.0));
+        assert_eq!(
+            map.path_to(&Vec3I { x: 6, y: 4, z: 2 }).unwrap(),
+            vec![
+                starts[1],
+                Vec3I { x: 6, y: 5, z: 2 },
+                Vec3I { x: 6, y: 4, z: 2 }
+            ]
+        );
+
+        assert!(reachable_within_i(&grid, &Vec3I { x: 9, y: 0, z: 0 }, &rules, 5.0).is_empty());
+    }
+}
@@ -0,0 +1,92 @@
+use std::{fmt, time::Duration};
+
+use crate::{grid::grid::PathfindingGrid, primitive::vector::Vec3I};
+
+// Search statistics reported by every engine, for found paths and failed searches alike.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PathStats {
+    pub nodes_expanded: usize,
+    pub open_list_peak: usize,
+    pub elapsed: Duration,
+}
+
+impl PathStats {
+    pub fn record_open_list(&mut self, len: usize) {
+        self.open_list_peak = self.open_list_peak.max(len);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathResult {
+    // Every cell from start to goal, both included. Any angle engines only return the waypoints,
+    // each in line of sight of the next.
+    pub path: Vec<Vec3I>,
+    pub cost: f32,
+    pub stats: PathStats,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathError {
+    StartBlocked,
+    GoalBlocked,
+    // Every cell reachable from start was searched without finding the goal.
+    GoalUnreachable { stats: PathStats },
+    // The search gave up before finding the goal or proving it unreachable.
+    BudgetExhausted { stats: PathStats },
+    // The workers of a path service stopped before resolving the request.
+    ServiceStopped,
+    // The engine panicked while a path service resolved the request.
+    EnginePanicked,
+}
+
+impl PathError {
+    pub fn stats(&self) -> Option<&PathStats> {
+        match self {
+            PathError::StartBlocked
+            | PathError::GoalBlocked
+            | PathError::ServiceStopped
+            | PathError::EnginePanicked => None,
+            PathError::GoalUnreachable { stats } | PathError::BudgetExhausted { stats } => {
+                Some(stats)
+            }
+        }
+    }
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathError::StartBlocked => write!(f, "path start can not be entered"),
+            PathError::GoalBlocked => write!(f, "path goal can not be entered"),
+            PathError::GoalUnreachable { stats } => write!(
+                f,
+                "path goal is unreachable ({} nodes expanded)",
+                stats.nodes_expanded
+            ),
+            PathError::BudgetExhausted { stats } => write!(
+                f,
+                "path search budget exhausted ({} nodes expanded)",
+                stats.nodes_expanded
+            ),
+            PathError::ServiceStopped => write!(f, "path service stopped before resolving"),
+            PathError::EnginePanicked => write!(f, "path engine panicked while resolving"),
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
+// Rejects searches whose start or goal can not be entered, shared by every engine.
+pub fn check_path_endpoints(
+    grid: &dyn PathfindingGrid,
+    start: &Vec3I,
+    goal: &Vec3I,
+) -> Result<(), PathError> {
+    if !grid.is_valid_path_node_i(start) {
+        return Err(PathError::StartBlocked);
+    }
+    if !grid.is_valid_path_node_i(goal) {
+        return Err(PathError::GoalBlocked);
+    }
+    Ok(())
+}
@@ -0,0 +1,292 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Arc, Mutex, PoisonError, RwLock,
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    grid::grid::PathfindingGrid,
+    path::{
+        query::PathQuery,
+        result::{PathError, PathResult},
+    },
+};
+
+// Resolves path queries against a shared grid on a pool of worker threads. Workers hold a read
+// lock on the grid while searching, edits made through the write lock wait for the searches in
+// flight and are seen by every later search. A panicking engine only fails its own request, the
+// worker goes on with the next one.
+
+pub type PathRequestId = u64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathResponse {
+    pub id: PathRequestId,
+    pub result: Result<PathResult, PathError>,
+}
+
+struct PathJob {
+    id: PathRequestId,
+    query: PathQuery,
+    reply: Sender<PathResponse>,
+}
+
+// Receives the result of a single request.
+pub struct PathHandle {
+    id: PathRequestId,
+    receiver: Receiver<PathResponse>,
+    // Kept once received, the worker hangs up after replying.
+    result: Option<Result<PathResult, PathError>>,
+}
+
+impl PathHandle {
+    pub fn id(&self) -> PathRequestId {
+        self.id
+    }
+
+    // Returns the result if the request has been resolved, without blocking. Safe to poll again
+    // once resolved, every later call returns the same result.
+    pub fn try_result(&mut self) -> Option<Result<PathResult, PathError>> {
+        if self.result.is_none() {
+            self.result = match self.receiver.try_recv() {
+                Ok(response) => Some(response.result),
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => Some(Err(PathError::ServiceStopped)),
+            };
+        }
+
+        self.result.clone()
+    }
+
+    // Blocks until the request has been resolved.
+    pub fn wait(self) -> Result<PathResult, PathError> {
+        if let Some(result) = self.result {
+            return result;
+        }
+
+        self.receiver
+            .recv()
+            .map_or(Err(PathError::ServiceStopped), |response| response.result)
+    }
+}
+
+pub struct PathService<G: PathfindingGrid + 'static> {
+    grid: Arc<RwLock<G>>,
+    jobs: Option<Sender<PathJob>>,
+    workers: Vec<JoinHandle<()>>,
+    next_id: AtomicU64,
+}
+
+impl<G: PathfindingGrid + 'static> PathService<G> {
+    pub fn new(grid: G, worker_count: usize) -> Self {
+        Self::from_shared(Arc::new(RwLock::new(grid)), worker_count)
+    }
+
+    // Runs at least one worker.
+    pub fn from_shared(grid: Arc<RwLock<G>>, worker_count: usize) -> Self {
+        let (jobs, receiver) = mpsc::channel::<PathJob>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..worker_count.max(1))
+            .map(|_| {
+                let grid = Arc::clone(&grid);
+                let receiver = Arc::clone(&receiver);
+                thread::spawn(move || path_service_worker(&grid, &receiver))
+            })
+            .collect();
+
+        Self {
+            grid,
+            jobs: Some(jobs),
+            workers,
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    pub fn grid(&self) -> &Arc<RwLock<G>> {
+        &self.grid
+    }
+
+    pub fn worker_count(&self) -> usize {
+        self.workers.len()
+    }
+
+    // Queues query, its result is delivered to the returned handle. Fails with
+    // PathError::ServiceStopped once every worker stopped.
+    pub fn request(&self, query: PathQuery) -> Result<PathHandle, PathError> {
+        let (reply, receiver) = mpsc::channel();
+        let id = self.submit(query, reply)?;
+        Ok(PathHandle {
+            id,
+            receiver,
+            result: None,
+        })
+    }
+
+    // Queues query, its result is sent to results along with the returned id. A single channel
+    // can collect the results of any number of requests.
+    pub fn request_to(
+        &self,
+        query: PathQuery,
+        results: &Sender<PathResponse>,
+    ) -> Result<PathRequestId, PathError> {
+        self.submit(query, results.clone())
+    }
+
+    fn submit(
+        &self,
+        query: PathQuery,
+        reply: Sender<PathResponse>,
+    ) -> Result<PathRequestId, PathError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.jobs
+            .as_ref()
+            .ok_or(PathError::ServiceStopped)?
+            .send(PathJob { id, query, reply })
+            .map_err(|_| PathError::ServiceStopped)?;
+        Ok(id)
+    }
+}
+
+impl<G: PathfindingGrid + 'static> Drop for PathService<G> {
+    // Lets the workers finish the queued requests, then joins them.
+    fn drop(&mut self) {
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn path_service_worker<G: PathfindingGrid>(grid: &RwLock<G>, jobs: &Mutex<Receiver<PathJob>>) {
+    loop {
+        let job = jobs.lock().unwrap_or_else(PoisonError::into_inner).recv();
+        let Ok(job) = job else {
+            return;
+        };
+
+        let grid = grid.read().unwrap_or_else(PoisonError::into_inner);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| grid.compute_path_i(&job.query)))
+            .unwrap_or(Err(PathError::EnginePanicked));
+        drop(grid);
+
+        // The requester may have dropped its handle or channel already.
+        let _ = job.reply.send(PathResponse { id: job.id, result });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        grid::grid::VoxelGrid,
+        path::{fixture::open_grid, path::PathfindingEngine},
+        primitive::{alias::TransformInt, vector::Vec3I},
+    };
+
+    fn query_to(x: TransformInt) -> PathQuery {
+        PathQuery::new(&Vec3I { x: 0, y: 0, z: 0 }, &Vec3I { x, y: 0, z: 0 })
+    }
+
+    #[test]
+    fn resolves_requests_through_handles() {
+        let service = PathService::new(open_grid(&Vec3I { x: 16, y: 1, z: 1 }), 3);
+        assert_eq!(service.worker_count(), 3);
+
+        let handles: Vec<_> = (0..16)
+            .map(|x| service.request(query_to(x)).unwrap())
+            .collect();
+        for (x, handle) in handles.into_iter().enumerate() {
+            assert_eq!(handle.id(), x as PathRequestId);
+            assert_eq!(handle.wait().unwrap().path.len(), x + 1);
+        }
+
+        let handle = service.request(query_to(16)).unwrap();
+        assert_eq!(handle.wait(), Err(PathError::GoalBlocked));
+    }
+
+    #[test]
+    fn resolves_requests_through_a_channel() {
+        let service = PathService::new(open_grid(&Vec3I { x: 16, y: 1, z: 1 }), 2);
+        let (results, receiver) = mpsc::channel();
+
+        let ids: Vec<_> = (0..8)
+            .map(|x| service.request_to(query_to(x), &results).unwrap())
+            .collect();
+
+        let mut responses: Vec<_> = receiver.iter().take(ids.len()).collect();
+        responses.sort_by_key(|response| response.id);
+        for (response, id) in responses.iter().zip(&ids) {
+            assert_eq!(response.id, *id);
+            assert_eq!(
+                response.result.as_ref().unwrap().path.len(),
+                *id as usize + 1
+            );
+        }
+    }
+
+    #[test]
+    fn later_requests_see_grid_edits() {
+        let service = PathService::new(open_grid(&Vec3I { x: 16, y: 1, z: 1 }), 1);
+        assert!(service.request(query_to(8)).unwrap().wait().is_ok());
+
+        service.grid().write().unwrap().delete_cell(4, 0, 0);
+        assert!(matches!(
+            service.request(query_to(8)).unwrap().wait(),
+            Err(PathError::GoalUnreachable { .. })
+        ));
+    }
+
+    #[test]
+    fn resolved_handles_can_be_polled_again() {
+        let service = PathService::new(open_grid(&Vec3I { x: 16, y: 1, z: 1 }), 1);
+        let mut handle = service.request(query_to(4)).unwrap();
+
+        let result = loop {
+            if let Some(result) = handle.try_result() {
+                break result;
+            }
+            thread::yield_now();
+        };
+        assert_eq!(result.as_ref().unwrap().path.len(), 5);
+        assert_eq!(handle.try_result(), Some(result.clone()));
+        assert_eq!(handle.wait(), result);
+    }
+
+    struct PanickingEngine;
+
+    impl PathfindingEngine for PanickingEngine {
+        fn compute_path_i(
+            &self,
+            _grid: &dyn PathfindingGrid,
+            _query: &PathQuery,
+        ) -> Result<PathResult, PathError> {
+            panic!("engine failure");
+        }
+    }
+
+    #[test]
+    fn engine_panics_are_reported_as_errors() {
+        let service = PathService::new(open_grid(&Vec3I { x: 16, y: 1, z: 1 }), 1);
+        let mut handle = service
+            .request(query_to(4).engine(PanickingEngine))
+            .unwrap();
+
+        let result = loop {
+            if let Some(result) = handle.try_result() {
+                break result;
+            }
+            thread::yield_now();
+        };
+        assert_eq!(result, Err(PathError::EnginePanicked));
+        assert_eq!(handle.try_result(), Some(result));
+
+        // The worker survived the panic and resolves the next request.
+        let result = service.request(query_to(4)).and_then(PathHandle::wait);
+        assert_eq!(result.unwrap().path.len(), 5);
+    }
+}
@@ -0,0 +1,611 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    time::Instant,
+};
+
+use crate::{
+    grid::grid::PathfindingGrid,
+    path::{
+        neighborhood::{step, step_length, CornerCutting, MovementRules, Neighborhood},
+        node::{reconstruct_path, OpenNode},
+        path::PathfindingEngine,
+        query::{Heuristic, PathQuery},
+        result::{check_path_endpoints, PathError, PathResult, PathStats},
+    },
+    primitive::{alias::TransformInt, vector::Vec3I},
+};
+
+// Any angle searches over voxel grids. The neighbors of a cell are searched like A* does, but a
+// neighbor in line of sight of the parent of the cell is linked straight to that parent, the
+// returned path only contains the waypoints where the path changes direction.
+//
+// Segments between waypoints pay the traversal cost of every cell they pass through for the
+// length spent inside of it, on uniform traversal costs path costs are Euclidean lengths. The
+// octile and Manhattan heuristics overestimate such paths, the estimate of the query is clamped to
+// the (weighted) Euclidean distance so every heuristic stays admissible. With Neighborhood::Six
+// only segments along a single axis are taken, an agent moving between face neighbors can not
+// follow any other line.
+//
+// Theta* checks the line of sight for every searched neighbor, Lazy Theta* assumes it and only
+// checks once a cell is expanded, which saves most of the checks at the price of slightly longer
+// paths in cluttered areas.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ThetaStar;
+
+impl PathfindingEngine for ThetaStar {
+    fn compute_path_i(
+        &self,
+        grid: &dyn PathfindingGrid,
+        query: &PathQuery,
+    ) -> Result<PathResult, PathError> {
+        theta_star_i(grid, query)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LazyThetaStar;
+
+impl PathfindingEngine for LazyThetaStar {
+    fn compute_path_i(
+        &self,
+        grid: &dyn PathfindingGrid,
+        query: &PathQuery,
+    ) -> Result<PathResult, PathError> {
+        lazy_theta_star_i(grid, query)
+    }
+}
+
+#[derive(Copy, Clone)]
+struct ThetaIState {
+    cost: f32,
+    // The start is its own parent.
+    parent: Vec3I,
+    closed: bool,
+}
+
+pub fn theta_star_i(
+    grid: &dyn PathfindingGrid,
+    query: &PathQuery,
+) -> Result<PathResult, PathError> {
+    theta_i(grid, query, false)
+}
+
+pub fn lazy_theta_star_i(
+    grid: &dyn PathfindingGrid,
+    query: &PathQuery,
+) -> Result<PathResult, PathError> {
+    theta_i(grid, query, true)
+}
+
+// Whether a straight line between the centers of from and to only passes through valid path
+// nodes.
+pub fn line_of_sight_i(
+    grid: &dyn PathfindingGrid,
+    corner_cutting: CornerCutting,
+    from: &Vec3I,
+    to: &Vec3I,
+) -> bool {
+    line_of_sight_cost_i(grid, corner_cutting, from, to).is_some()
+}
+
+// Cost of a straight line between the centers of from and to, None if it passes through a cell
+// that is not a valid path node. Lines passing exactly along an edge or through a corner touch
+// every cell around it, with CornerCutting::Forbid all of them have to be valid path nodes.
+pub fn line_of_sight_cost_i(
+    grid: &dyn PathfindingGrid,
+    corner_cutting: CornerCutting,
+    from: &Vec3I,
+    to: &Vec3I,
+) -> Option<f32> {
+    let delta = theta_i_axes(&Vec3I {
+        x: to.x - from.x,
+        y: to.y - from.y,
+        z: to.z - from.z,
+    });
+    let signs = delta.map(|d| d.signum());
+    let lengths = delta.map(|d| d.abs());
+    let length = lengths
+        .iter()
+        .map(|&d| (d as f32).powi(2))
+        .sum::<f32>()
+        .sqrt();
+
+    let mut cell = theta_i_axes(from);
+    let mut crossed: [TransformInt; 3] = [0; 3];
+    let mut cell_cost = grid.traversal_cost_i(from)?;
+    let mut cost = 0.0;
+    let mut entered_at = 0.0;
+
+    // Walks the cells in the order the line enters them. The line leaves the current cell along
+    // axis a at t = (2 * crossed[a] + 1) / (2 * lengths[a]), compared exactly by cross
+    // multiplication. The products are widened, they overflow TransformInt on long lines.
+    while theta_i_position(&cell) != *to {
+        let mut tied = [false; 3];
+        let mut first: Option<usize> = None;
+        let exit = |axis: usize, other: usize| {
+            (2 * i128::from(crossed[axis]) + 1) * i128::from(lengths[other])
+        };
+        for axis in 0..3 {
+            if crossed[axis] == lengths[axis] {
+                continue;
+            }
+
+            let ordering = first.map(|other| exit(axis, other).cmp(&exit(other, axis)));
+            match ordering {
+                Some(Ordering::Greater) => {}
+                Some(Ordering::Equal) => tied[axis] = true,
+                Some(Ordering::Less) | None => {
+                    tied = [false; 3];
+                    tied[axis] = true;
+                    first = Some(axis);
+                }
+            }
+        }
+
+        let axis = first?;
+        let left_at = (2.0 * crossed[axis] as f32 + 1.0) / (2.0 * lengths[axis] as f32);
+        cost += (left_at - entered_at) * length * cell_cost;
+        entered_at = left_at;
+
+        if corner_cutting == CornerCutting::Forbid {
+            let mut axes = [0; 3];
+            let mut count = 0;
+            for axis in (0..3).filter(|&axis| tied[axis]) {
+                axes[count] = axis;
+                count += 1;
+            }
+            for subset in 1..(1 << count) - 1 {
+                let mut side = cell;
+                for (bit, &axis) in axes[..count].iter().enumerate() {
+                    if subset & (1 << bit) != 0 {
+                        side[axis] += signs[axis];
+                    }
+                }
+                if !grid.is_valid_path_node_i(&theta_i_position(&side)) {
+                    return None;
+                }
+            }
+        }
+
+        for axis in 0..3 {
+            if tied[axis] {
+                cell[axis] += signs[axis];
+                crossed[axis] += 1;
+            }
+        }
+        cell_cost = grid.traversal_cost_i(&theta_i_position(&cell))?;
+    }
+
+    Some(cost + (1.0 - entered_at) * length * cell_cost)
+}
+
+fn theta_i(
+    grid: &dyn PathfindingGrid,
+    query: &PathQuery,
+    lazy: bool,
+) -> Result<PathResult, PathError> {
+    let started = Instant::now();
+    let mut stats = PathStats::default();
+    let (start, goal) = (&query.start, &query.goal);
+    check_path_endpoints(grid, start, goal)?;
+
+    let rules = &query.rules;
+    let mut over_max_path_cost = false;
+    let mut states = HashMap::<Vec3I, ThetaIState>::new();
+    let mut open_list = BinaryHeap::new();
+
+    states.insert(
+        *start,
+        ThetaIState {
+            cost: 0.0,
+            parent: *start,
+            closed: false,
+        },
+    );
+    open_list.push(OpenNode {
+        position: *start,
+        cost: theta_i_estimate(query, start),
+        cost_so_far: 0.0,
+    });
+    stats.record_open_list(open_list.len());
+
+    while let Some(current) = open_list.pop() {
+        let mut state = states[&current.position];
+        if state.closed || current.cost_so_far > state.cost {
+            continue;
+        }
+
+        // The parent was assumed to be in line of sight, fall back to the cheapest expanded
+        // neighbor if it is not.
+        if lazy && current.position != *start {
+            match line_of_sight_cost_i(grid, rules.corner_cutting, &state.parent, &current.position)
+            {
+                Some(cost) => state.cost = states[&state.parent].cost + cost,
+                None => {
+                    if let Some((parent, cost)) =
+                        theta_i_closest_expanded_neighbor(grid, rules, &states, &current.position)
+                    {
+                        state.parent = parent;
+                        state.cost = cost;
+                    }
+                }
+            }
+        }
+
+        state.closed = true;
+        states.insert(current.position, state);
+
+        if current.position == *goal {
+            stats.elapsed = started.elapsed();
+            return Ok(PathResult {
+                path: reconstruct_path(*goal, |current| {
+                    let parent = states[&current].parent;
+                    (parent != current).then_some(parent)
+                }),
+                cost: state.cost,
+                stats,
+            });
+        }
+
+        if query.is_expansion_budget_exhausted(&stats) {
+            stats.elapsed = started.elapsed();
+            return Err(PathError::BudgetExhausted { stats });
+        }
+        stats.nodes_expanded += 1;
+
+        let parent_cost = states[&state.parent].cost;
+        for direction in rules.neighborhood.directions() {
+            let Some(step_cost) = theta_i_step_cost(grid, rules, &current.position, direction)
+            else {
+                continue;
+            };
+
+            let position = step(&current.position, direction);
+            if states.get(&position).is_some_and(|state| state.closed) {
+                continue;
+            }
+
+            let mut parent = current.position;
+            let mut new_cost = state.cost + step_cost;
+            if current.position != *start
+                && theta_i_walkable_line(rules.neighborhood, &state.parent, &position)
+            {
+                let shortcut = if lazy {
+                    theta_i_assumed_cost(grid, &state.parent, &position)
+                } else {
+                    line_of_sight_cost_i(grid, rules.corner_cutting, &state.parent, &position)
+                };
+
+                if let Some(cost) = shortcut {
+                    if lazy || parent_cost + cost <= new_cost {
+                        parent = state.parent;
+                        new_cost = parent_cost + cost;
+                    }
+                }
+            }
+
+            if query.exceeds_max_path_cost(new_cost) {
+                over_max_path_cost = true;
+                continue;
+            }
+
+            if states
+                .get(&position)
+                .is_some_and(|state| state.cost <= new_cost)
+            {
+                continue;
+            }
+
+            states.insert(
+                position,
+                ThetaIState {
+                    cost: new_cost,
+                    parent,
+                    closed: false,
+                },
+            );
+            open_list.push(OpenNode {
+                position,
+                cost: new_cost + theta_i_estimate(query, &position),
+                cost_so_far: new_cost,
+            });
+            stats.record_open_list(open_list.len());
+        }
+    }
+
+    stats.elapsed = started.elapsed();
+    if over_max_path_cost {
+        Err(PathError::BudgetExhausted { stats })
+    } else {
+        Err(PathError::GoalUnreachable { stats })
+    }
+}
+
+// Cost of a single step between neighbors, split evenly between the two cells like
+// line_of_sight_cost_i does.
+fn theta_i_step_cost(
+    grid: &dyn PathfindingGrid,
+    rules: &MovementRules,
+    position: &Vec3I,
+    direction: &Vec3I,
+) -> Option<f32> {
+    if !rules.allows_step(position, direction, |cell| grid.is_valid_path_node_i(cell)) {
+        return None;
+    }
+
+    let from = grid.traversal_cost_i(position)?;
+    let to = grid.traversal_cost_i(&step(position, direction))?;
+    Some(step_length(direction) * (from + to) * 0.5)
+}
+
+// Estimate of the query, at most the weighted straight line distance to the goal. Paths along
+// the axes are never shorter than the octile distance of face neighbors, which is kept.
+fn theta_i_estimate(query: &PathQuery, position: &Vec3I) -> f32 {
+    if query.rules.neighborhood == Neighborhood::Six {
+        return query.estimate(position);
+    }
+
+    let euclidean = Heuristic::Euclidean.estimate(query.rules.neighborhood, position, &query.goal);
+    query
+        .estimate(position)
+        .min(euclidean * query.heuristic_weight)
+}
+
+// Cost of a straight line assumed to be in line of sight, only the endpoints are looked at.
+fn theta_i_assumed_cost(grid: &dyn PathfindingGrid, from: &Vec3I, to: &Vec3I) -> Option<f32> {
+    let dx = (to.x - from.x) as f32;
+    let dy = (to.y - from.y) as f32;
+    let dz = (to.z - from.z) as f32;
+    let length = (dx * dx + dy * dy + dz * dz).sqrt();
+
+    let from = grid.traversal_cost_i(from)?;
+    let to = grid.traversal_cost_i(to)?;
+    Some(length * (from + to) * 0.5)
+}
+
+// Whether an agent moving with neighborhood can follow the straight line between from and to,
+// agents limited to face neighbors can only follow lines along a single axis.
+fn theta_i_walkable_line(neighborhood: Neighborhood, from: &Vec3I, to: &Vec3I) -> bool {
+    if neighborhood != Neighborhood::Six {
+        return true;
+    }
+
+    [from.x != to.x, from.y != to.y, from.z != to.z]
+        .iter()
+        .filter(|&&changed| changed)
+        .count()
+        <= 1
+}
+
+fn theta_i_closest_expanded_neighbor(
+    grid: &dyn PathfindingGrid,
+    rules: &MovementRules,
+    states: &HashMap<Vec3I, ThetaIState>,
+    position: &Vec3I,
+) -> Option<(Vec3I, f32)> {
+    rules
+        .neighborhood
+        .directions()
+        .iter()
+        .filter_map(|direction| {
+            let neighbor = step(position, direction);
+            let state = states.get(&neighbor).filter(|state| state.closed)?;
+            let back = Vec3I {
+                x: -direction.x,
+                y: -direction.y,
+                z: -direction.z,
+            };
+            let cost = theta_i_step_cost(grid, rules, &neighbor, &back)?;
+            Some((neighbor, state.cost + cost))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+fn theta_i_axes(position: &Vec3I) -> [TransformInt; 3] {
+    [position.x, position.y, position.z]
+}
+
+fn theta_i_position(axes: &[TransformInt; 3]) -> Vec3I {
+    Vec3I {
+        x: axes[0],
+        y: axes[1],
+        z: axes[2],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        grid::grid::VoxelGrid,
+        path::{astar::astar_i, fixture::open_grid, path::PathfindingType},
+        primitive::{cuboid::CuboidI, vector::Vec3F},
+    };
+
+    fn euclidean(a: &Vec3I, b: &Vec3I) -> f32 {
+        Heuristic::Euclidean.estimate(Default::default(), a, b)
+    }
+
+    #[test]
+    fn line_of_sight_walks_the_voxels_on_the_line() {
+        let mut grid = open_grid(&Vec3I { x: 5, y: 5, z: 1 });
+        let origin = Vec3I { x: 0, y: 0, z: 0 };
+
+        let cost = line_of_sight_cost_i(
+            &grid,
+            CornerCutting::Allow,
+            &origin,
+            &Vec3I { x: 4, y: 0, z: 0 },
+        );
+        assert_eq!(cost, Some(4.0));
+
+        let far = Vec3I { x: 4, y: 2, z: 0 };
+        let cost = line_of_sight_cost_i(&grid, CornerCutting::Allow, &origin, &far).unwrap();
+        assert!((cost - euclidean(&origin, &far)).abs() < 1e-5);
+
+        grid.delete_cell(2, 1, 0);
+        assert!(!line_of_sight_i(&grid, CornerCutting::Allow, &origin, &far));
+
+        // Only touches the corner of (2, 1, 0).
+        let diagonal = Vec3I { x: 4, y: 4, z: 0 };
+        assert!(line_of_sight_i(
+            &grid,
+            CornerCutting::Allow,
+            &origin,
+            &diagonal
+        ));
+        assert!(!line_of_sight_i(
+            &grid,
+            CornerCutting::Forbid,
+            &origin,
+            &diagonal
+        ));
+    }
+
+    #[test]
+    fn open_space_needs_no_waypoints() {
+        let grid = open_grid(&Vec3I { x: 8, y: 8, z: 8 });
+        let start = Vec3I { x: 0, y: 0, z: 0 };
+        let goal = Vec3I { x: 7, y: 3, z: 5 };
+        let query = PathQuery::new(&start, &goal).heuristic(Heuristic::Euclidean);
+
+        for engine in [PathfindingType::ThetaStar, PathfindingType::LazyThetaStar] {
+            let result = grid.compute_path_i(&query.clone().engine(engine)).unwrap();
+            assert_eq!(result.path, vec![start, goal]);
+            assert!((result.cost - euclidean(&start, &goal)).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn waypoints_around_obstacles_stay_in_line_of_sight() {
+        // A wall at x = 4 with a gap at the top.
+        let mut grid = open_grid(&Vec3I { x: 9, y: 9, z: 1 });
+        for y in 0..8 {
+            grid.delete_cell(4, y, 0);
+        }
+
+        let start = Vec3I { x: 0, y: 0, z: 0 };
+        let goal = Vec3I { x: 8, y: 0, z: 0 };
+        let query = PathQuery::new(&start, &goal).heuristic(Heuristic::Euclidean);
+        let expected = astar_i(&grid, &query).unwrap();
+
+        for engine in [PathfindingType::ThetaStar, PathfindingType::LazyThetaStar] {
+            let result = grid.compute_path_i(&query.clone().engine(engine)).unwrap();
+            assert_eq!(result.path.first(), Some(&start));
+            assert_eq!(result.path.last(), Some(&goal));
+            assert!(result.path.len() < expected.path.len());
+            assert!(result.cost < expected.cost);
+
+            let mut cost = 0.0;
+            for pair in result.path.windows(2) {
+                cost += line_of_sight_cost_i(&grid, CornerCutting::Allow, &pair[0], &pair[1])
+                    .expect("waypoints out of sight");
+            }
+            assert!((result.cost - cost).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn default_heuristic_keeps_paths_shortest() {
+        let mut grid = open_grid(&Vec3I { x: 12, y: 12, z: 2 });
+        for y in 2..12 {
+            grid.delete_cell(5, y, 0);
+            grid.delete_cell(8, 11 - y, 1);
+        }
+
+        let start = Vec3I { x: 0, y: 11, z: 0 };
+        let goal = Vec3I { x: 11, y: 0, z: 1 };
+        let query = PathQuery::new(&start, &goal);
+        for engine in [PathfindingType::ThetaStar, PathfindingType::LazyThetaStar] {
+            let query = query.clone().engine(engine);
+            let result = grid.compute_path_i(&query).unwrap();
+            let expected = grid
+                .compute_path_i(&query.heuristic(Heuristic::Euclidean))
+                .unwrap();
+            assert!((result.cost - expected.cost).abs() < 1e-4);
+        }
+    }
+
+    // Unbounded grid where every cell can be entered.
+    struct OpenSpace;
+
+    impl PathfindingGrid for OpenSpace {
+        fn traversal_cost_i(&self, _position: &Vec3I) -> Option<f32> {
+            Some(1.0)
+        }
+
+        fn position_i(&self) -> Vec3I {
+            Vec3I { x: 0, y: 0, z: 0 }
+        }
+
+        fn position_f(&self) -> Vec3F {
+            Vec3F {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            }
+        }
+
+        fn as_cuboid_cells(&self) -> Vec<CuboidI> {
+            Vec::new()
+        }
+
+        fn compute_path_i(&self, query: &PathQuery) -> Result<PathResult, PathError> {
+            query.engine.compute_path_i(self, query)
+        }
+    }
+
+    #[test]
+    fn long_lines_of_sight_do_not_overflow() {
+        let from = Vec3I { x: 0, y: 0, z: 0 };
+        let to = Vec3I {
+            x: 100_000,
+            y: 70_001,
+            z: 3,
+        };
+        let cost = line_of_sight_cost_i(&OpenSpace, CornerCutting::Forbid, &from, &to).unwrap();
+        assert!((cost / euclidean(&from, &to) - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn face_neighbors_only_follow_axis_lines() {
+        let mut grid = open_grid(&Vec3I {
+            x: 16,
+            y: 16,
+            z: 16,
+        });
+        grid.delete_cell(7, 7, 7);
+        let start = Vec3I { x: 0, y: 0, z: 0 };
+        let goal = Vec3I {
+            x: 15,
+            y: 15,
+            z: 15,
+        };
+        let query = PathQuery::new(&start, &goal).neighborhood(Neighborhood::Six);
+        let expected = astar_i(&grid, &query).unwrap();
+
+        for engine in [PathfindingType::ThetaStar, PathfindingType::LazyThetaStar] {
+            let result = grid.compute_path_i(&query.clone().engine(engine)).unwrap();
+            assert_eq!(result.path.last(), Some(&goal));
+            assert!((result.cost - expected.cost).abs() < 1e-3);
+            assert!(result.stats.nodes_expanded <= expected.stats.nodes_expanded * 2);
+            for pair in result.path.windows(2) {
+                assert!(theta_i_walkable_line(Neighborhood::Six, &pair[0], &pair[1]));
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_blocked_endpoints() {
+        let grid = open_grid(&Vec3I { x: 3, y: 3, z: 3 });
+        let query = PathQuery::new(&Vec3I { x: 0, y: 0, z: 0 }, &Vec3I { x: 5, y: 0, z: 0 });
+
+        assert_eq!(theta_star_i(&grid, &query), Err(PathError::GoalBlocked));
+        assert_eq!(
+            lazy_theta_star_i(&grid, &query),
+            Err(PathError::GoalBlocked)
+        );
+    }
+}
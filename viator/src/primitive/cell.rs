@@ -1,2 +1,6 @@
+use crate::path::cost::Traversable;
+
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Cell;
+
+impl Traversable for Cell {}
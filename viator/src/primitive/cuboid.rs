@@ -1,10 +1,132 @@
-use super::vector::{Vec3F, Vec3I};
+use super::{
+    alias::TransformInt,
+    vector::{Vec3F, Vec3I},
+};
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct CuboidI {
     pub position: Vec3I,
     pub extents: Vec3I,
 }
 
+impl CuboidI {
+    pub fn is_empty(&self) -> bool {
+        self.extents.x <= 0 || self.extents.y <= 0 || self.extents.z <= 0
+    }
+
+    pub fn volume(&self) -> usize {
+        if self.is_empty() {
+            return 0;
+        }
+
+        self.extents.x as usize * self.extents.y as usize * self.extents.z as usize
+    }
+
+    pub fn contains(&self, position: &Vec3I) -> bool {
+        position.x >= self.position.x
+            && position.y >= self.position.y
+            && position.z >= self.position.z
+            && position.x < self.position.x + self.extents.x
+            && position.y < self.position.y + self.extents.y
+            && position.z < self.position.z + self.extents.z
+    }
+
+    // Returns true if every cell of other is inside of self.
+    pub fn contains_cuboid(&self, other: &CuboidI) -> bool {
+        other.is_empty()
+            || (other.position.x >= self.position.x
+                && other.position.y >= self.position.y
+                && other.position.z >= self.position.z
+                && other.position.x + other.extents.x <= self.position.x + self.extents.x
+                && other.position.y + other.extents.y <= self.position.y + self.extents.y
+                && other.position.z + other.extents.z <= self.position.z + self.extents.z)
+    }
+
+    pub fn intersection(&self, other: &CuboidI) -> Option<CuboidI> {
+        let position = Vec3I {
+            x: self.position.x.max(other.position.x),
+            y: self.position.y.max(other.position.y),
+            z: self.position.z.max(other.position.z),
+        };
+        let end = Vec3I {
+            x: (self.position.x + self.extents.x).min(other.position.x + other.extents.x),
+            y: (self.position.y + self.extents.y).min(other.position.y + other.extents.y),
+            z: (self.position.z + self.extents.z).min(other.position.z + other.extents.z),
+        };
+
+        let intersection = CuboidI {
+            position,
+            extents: Vec3I {
+                x: end.x - position.x,
+                y: end.y - position.y,
+                z: end.z - position.z,
+            },
+        };
+
+        if intersection.is_empty() {
+            None
+        } else {
+            Some(intersection)
+        }
+    }
+
+    // Returns the position of the cell at index when iterating the cells of the cuboid, x varies
+    // fastest followed by the horizontal axis and then the up axis, matching the storage order of
+    // the voxel grids.
+    pub fn cell_at(&self, index: usize) -> Vec3I {
+        let index = index as TransformInt;
+        let extents = self.extents;
+
+        #[cfg(feature = "y_up")]
+        {
+            Vec3I {
+                x: self.position.x + index % extents.x,
+                y: self.position.y + index / (extents.x * extents.z),
+                z: self.position.z + (index / extents.x) % extents.z,
+            }
+        }
+
+        #[cfg(feature = "z_up")]
+        {
+            Vec3I {
+                x: self.position.x + index % extents.x,
+                y: self.position.y + (index / extents.x) % extents.y,
+                z: self.position.z + index / (extents.x * extents.y),
+            }
+        }
+    }
+
+    // Inverse of cell_at, returns None when position is outside of the cuboid.
+    pub fn cell_index(&self, position: &Vec3I) -> Option<usize> {
+        if !self.contains(position) {
+            return None;
+        }
+
+        let x = (position.x - self.position.x) as usize;
+        let y = (position.y - self.position.y) as usize;
+        let z = (position.z - self.position.z) as usize;
+        let width = self.extents.x as usize;
+
+        #[cfg(feature = "y_up")]
+        {
+            let depth = self.extents.z as usize;
+            Some(x + (z * width) + (y * width * depth))
+        }
+
+        #[cfg(feature = "z_up")]
+        {
+            let height = self.extents.y as usize;
+            Some(x + (y * width) + (z * width * height))
+        }
+    }
+
+    // Iterates the position of every cell inside of the cuboid in the order of cell_at.
+    pub fn iter_cells(&self) -> impl Iterator<Item = Vec3I> {
+        let cuboid = *self;
+        (0..cuboid.volume()).map(move |index| cuboid.cell_at(index))
+    }
+}
+
 pub struct CuboidF {
     pub position: Vec3F,
     pub extents: Vec3F,
@@ -13,7 +13,7 @@ use system::{
     ui::fps_text_system::fps_text_system,
 };
 use viator::{
-    grid::grid::PathfindingGrid,
+    grid::grid::{PathfindingGrid, VoxelGrid},
     grid::voxel::{
         dynamic_sized_voxel_grid::DynamicSizedVoxelGrid,
         static_sized_voxel_grid::StaticSizedVoxelGridLarge,
@@ -121,15 +121,16 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         y: 10,
         z: 10,
     };
-    let mut grid_boxed = Box::new(StaticSizedVoxelGridLarge::<Cell>::new(
-        &grid_pos, &grid_size,
-    ));
-    grid_boxed.create_cell(0, 0, 0, &Cell::default());
-    grid_boxed.create_cell(1, 0, 0, &Cell::default());
-    grid_boxed.create_cell(0, 1, 0, &Cell::default());
-    grid_boxed.create_cell(0, 2, 0, &Cell::default());
-    grid_boxed.create_cell(0, 3, 0, &Cell::default());
-    grid_boxed.create_cell(0, 0, 1, &Cell::default());
+    let mut grid_boxed = Box::new(
+        StaticSizedVoxelGridLarge::<Cell>::new(&grid_pos, &grid_size)
+            .expect("grid extents exceed the static grid capacity"),
+    );
+    grid_boxed.create_cell(2, 0, 2, &Cell::default());
+    grid_boxed.create_cell(3, 0, 2, &Cell::default());
+    grid_boxed.create_cell(2, 1, 2, &Cell::default());
+    grid_boxed.create_cell(2, 2, 2, &Cell::default());
+    grid_boxed.create_cell(2, 3, 2, &Cell::default());
+    grid_boxed.create_cell(2, 0, 3, &Cell::default());
 
     let draw_grid_component = GridDrawComponent::new(grid_boxed, Color::GOLD);
     commands.spawn_empty().insert(draw_grid_component);